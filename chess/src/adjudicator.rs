@@ -0,0 +1,122 @@
+use crate::board::{Board, Status};
+use crate::color::Color;
+
+//#################################################################################################
+//
+//                                       enum GameResult
+//
+//#################################################################################################
+
+/// The result of a finished game.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GameResult {
+    Win(Color),
+    Draw,
+}
+
+//#################################################################################################
+//
+//                                      struct Adjudicator
+//
+//#################################################################################################
+
+/// A policy to end games early, as used in engine tournaments: a game is adjudicated
+/// as a win when the evaluations stay beyond win_score for win_plies consecutive plies,
+/// and as a draw when they stay within draw_score for draw_plies consecutive plies.
+/// A number of plies of 0 disables the corresponding rule.
+#[derive(Clone, Debug)]
+pub struct Adjudicator {
+    /// The number of consecutive plies the evaluations must be drawish for.
+    pub draw_plies: usize,
+    /// The maximum absolute evaluation, in centipawns, considered drawish.
+    pub draw_score: i32,
+    /// The number of consecutive plies the evaluations must be winning for.
+    pub win_plies: usize,
+    /// The minimum absolute evaluation, in centipawns, considered winning.
+    pub win_score: i32,
+}
+
+// ================================ pub impl
+
+impl Adjudicator {
+    /// Returns the result of the game if it is over, either by the rules of chess (checkmate,
+    /// stalemate, insufficient material, fifty moves rule or threefold repetition) or by
+    /// adjudication. The history holds the evaluations of the game, one per ply and in
+    /// centipawns, from white's point of view.
+    pub fn adjudicate(&self, board: &Board, history: &[i32]) -> Option<GameResult> {
+        match board.status() {
+            Status::Win(color) => return Some(GameResult::Win(color)),
+            Status::Draw => return Some(GameResult::Draw),
+            Status::Playing => (),
+        }
+
+        // The evaluations of the last n plies, if there are enough of them.
+        let last = |n: usize| if n != 0 && history.len() >= n {
+            Some(&history[history.len() - n..])
+        } else {
+            None
+        };
+
+        if let Some(scores) = last(self.win_plies) {
+            if scores.iter().all(|&score| score >= self.win_score) {
+                return Some(GameResult::Win(Color::White));
+            }
+            if scores.iter().all(|&score| score <= -self.win_score) {
+                return Some(GameResult::Win(Color::Black));
+            }
+        }
+
+        if let Some(scores) = last(self.draw_plies) {
+            if scores.iter().all(|&score| score.abs() <= self.draw_score) {
+                return Some(GameResult::Draw);
+            }
+        }
+
+        None
+    }
+}
+
+// ================================ traits impl
+
+impl Default for Adjudicator {
+    /// Draw after 20 plies within 10 centipawns, win after 10 plies beyond 1000 centipawns.
+    fn default() -> Adjudicator {
+        Adjudicator {
+            draw_plies: 20,
+            draw_score: 10,
+            win_plies: 10,
+            win_score: 1000,
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::color::Color;
+    use super::{Adjudicator, GameResult};
+
+    #[test]
+    fn adjudicate() {
+        crate::init();
+
+        let adjudicator = Adjudicator::default();
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+
+        assert_eq!(adjudicator.adjudicate(&board, &[]), None);
+        assert_eq!(adjudicator.adjudicate(&board, &[5; 19]), None);
+        assert_eq!(adjudicator.adjudicate(&board, &[5; 20]), Some(GameResult::Draw));
+        assert_eq!(adjudicator.adjudicate(&board, &[-1200; 10]), Some(GameResult::Win(Color::Black)));
+        assert_eq!(adjudicator.adjudicate(&board, &[1200, 1200, 900, 1200, 1200, 1200, 1200, 1200, 1200, 1200]), None);
+
+        // Fool's mate, whatever the evaluations.
+        let board = Board::new("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert_eq!(adjudicator.adjudicate(&board, &[]), Some(GameResult::Win(Color::Black)));
+    }
+}
@@ -5,17 +5,22 @@ use anyhow::{Error, Result};
 
 use crate::attacks;
 use crate::bitboard::BitBoard;
-use crate::castle_rights::CastleMask;
-use crate::castle_rights::CastleRights;
+use crate::book;
+use crate::castle_rights::{CastleRights, CastleSetup, CastleSide};
 use crate::color::Color;
 use crate::cuckoo;
 use crate::en_passant::EnPassantSquare;
+use crate::move_list::MoveList;
 use crate::movegen;
 use crate::moves::Move;
+use crate::pawns;
 use crate::piece::Piece;
 use crate::square::Square;
 use crate::zobrist::Zobrist;
 
+/// The fen of the starting position of standard chess.
+pub const STARTPOS_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
 //#################################################################################################
 //
 //                                    struct StateInfo
@@ -39,6 +44,137 @@ impl Status {
     }
 }
 
+//#################################################################################################
+//
+//                                  enum IllegalMoveReason
+//
+//#################################################################################################
+
+/// The reason why a move was refused by Board::make_move() or Board::parse_move().
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IllegalMoveReason {
+    /// There is no piece on the starting square.
+    NoPiece,
+    /// The piece on the starting square belongs to the opponent.
+    NotYourPiece,
+    /// One of our own pieces is standing on the destination square.
+    OwnPieceOnDestination,
+    /// The piece can't reach the destination square.
+    Unreachable,
+    /// A pawn reaching the last rank must promote.
+    MissingPromotion,
+    /// Promoting is only possible to a knight, bishop, rook or queen, and for pawns reaching the last rank.
+    InvalidPromotion,
+    /// The castle rights for that side were lost.
+    NoCastleRights,
+    /// The rook is missing or there are pieces between the king and the rook.
+    CastleBlocked,
+    /// The king can't castle out of, through or into check.
+    CastleThroughCheck,
+    /// The king is in check, and the move neither captures nor blocks the checker.
+    DoesNotParryCheck,
+    /// The king would move into check.
+    IntoCheck,
+    /// The piece is pinned and the move would expose the king.
+    ExposesKing,
+}
+
+// ================================ traits impl
+
+impl fmt::Display for IllegalMoveReason {
+    /// Describes the reason in plain english.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            IllegalMoveReason::NoPiece => "There is no piece on that square.",
+            IllegalMoveReason::NotYourPiece => "That piece belongs to the opponent.",
+            IllegalMoveReason::OwnPieceOnDestination => "The destination square is occupied by one of your pieces.",
+            IllegalMoveReason::Unreachable => "That piece can't move to that square.",
+            IllegalMoveReason::MissingPromotion => "The pawn must promote.",
+            IllegalMoveReason::InvalidPromotion => "Invalid promotion.",
+            IllegalMoveReason::NoCastleRights => "Castling on that side is no longer allowed.",
+            IllegalMoveReason::CastleBlocked => "The path between the king and the rook is not clear.",
+            IllegalMoveReason::CastleThroughCheck => "The king can't castle out of, through or into check.",
+            IllegalMoveReason::DoesNotParryCheck => "The king is in check.",
+            IllegalMoveReason::IntoCheck => "The king would be in check.",
+            IllegalMoveReason::ExposesKing => "The piece is pinned.",
+        })
+    }
+}
+
+impl std::error::Error for IllegalMoveReason {}
+
+//#################################################################################################
+//
+//                                    struct MoveInfo
+//
+//#################################################################################################
+
+/// What changed on the board when a move was done, as returned by Board::do_move_info().
+/// Together with the move, it is enough to undo it.
+#[derive(Clone, Copy, Debug)]
+pub struct MoveInfo {
+    /// True if the move reset the halfmove clock (pawn moves, captures and castling).
+    pub irreversible: bool,
+    /// The piece that was captured by the move, if any.
+    pub captured: Option<Piece>,
+    /// The castle rights before the move.
+    pub castle_rights: CastleRights,
+    /// The en passant square before the move.
+    pub ep_square: EnPassantSquare,
+    /// The halfmove clock before the move.
+    pub halfmove: u8,
+}
+
+//#################################################################################################
+//
+//                                    struct PastPosition
+//
+//#################################################################################################
+
+/// A position the game went through, as yielded by Board::position_history().
+#[derive(Clone, Copy, Debug)]
+pub struct PastPosition {
+    /// The zobrist key of the position, equal for repeated positions.
+    pub zobrist: Zobrist,
+    /// The fullmove number of the position.
+    pub fullmove: u16,
+    /// The side that was to move.
+    pub side_to_move: Color,
+}
+
+//#################################################################################################
+//
+//                                    trait EvalListener
+//
+//#################################################################################################
+
+/// A listener notified of the pieces placed, removed and moved by Board::do_move_with()
+/// and Board::undo_move_with(), to maintain an incremental evaluation (an accumulator for
+/// example) instead of recomputing it. The unit type is the listener that does nothing.
+pub trait EvalListener {
+    /// Called when a piece of that color is placed on the square.
+    fn on_place(&mut self, color: Color, piece: Piece, sq: Square);
+
+    /// Called when a piece of that color is removed from the square.
+    fn on_remove(&mut self, color: Color, piece: Piece, sq: Square);
+
+    /// Called when a piece of that color is moved from a square to the other.
+    fn on_move(&mut self, color: Color, piece: Piece, from: Square, to: Square);
+}
+
+// ================================ traits impl
+
+impl EvalListener for () {
+    #[inline(always)]
+    fn on_place(&mut self, _: Color, _: Piece, _: Square) {}
+
+    #[inline(always)]
+    fn on_remove(&mut self, _: Color, _: Piece, _: Square) {}
+
+    #[inline(always)]
+    fn on_move(&mut self, _: Color, _: Piece, _: Square, _: Square) {}
+}
+
 //#################################################################################################
 //
 //                                    struct StateInfo
@@ -108,6 +244,12 @@ pub struct Board {
     mailbox: [Option<(Color, Piece)>; 64],
     occ: Occupancy,
 
+    material_mg: i32,
+    material_eg: i32,
+    phase: u8,
+
+    castle_setup: CastleSetup,
+
     state: StateInfo,
     prev_states: Vec<StateInfo>,
 }
@@ -120,6 +262,102 @@ impl Board {
         Board::from_str(fen)
     }
 
+    /// Tries to parse the fen string into a board, rejecting a fullmove number
+    /// of 0 and out of range move counters.
+    pub fn from_fen_strict(fen: &str) -> Result<Board> {
+        Board::parse_fen(fen, true, false)
+    }
+
+    /// Tries to parse the fen string of a Chess960 position into a board. The king and the rooks
+    /// may start on any square of the back rank: K, Q, k and q designate the outermost rook on
+    /// the corresponding side of the king, and the Shredder-fen notation (HAha) is accepted too.
+    /// Unless the setup turns out to be the standard one, the board is displayed in Shredder-fen.
+    /// Castle moves are still encoded with the king landing on the g or c file, and make_move()
+    /// also accepts the king "capturing" it's own rook.
+    pub fn from_fen_960(fen: &str) -> Result<Board> {
+        Board::parse_fen(fen, false, true)
+    }
+
+    /// Tries to parse an epd record into a board: the four first fields of a fen string, followed
+    /// by operations separated by semicolons. The halfmove clock and fullmove number are read from
+    /// the hmvc and fmvn operations when present, and default to 0 and 1 otherwise. Other
+    /// operations (bm, id, ...) are ignored.
+    pub fn from_epd(epd: &str) -> Result<Board> {
+        let mut split = epd.trim().splitn(5, ' ');
+
+        let mut fields = Vec::with_capacity(6);
+        for _ in 0..4 {
+            fields.push(split.next().ok_or_else(|| Error::msg("not enough arguments in epd string"))?);
+        }
+
+        let (mut halfmove, mut fullmove) = ("0", "1");
+
+        for op in split.next().unwrap_or("").split(';') {
+            let mut op = op.trim().splitn(2, ' ');
+            let opcode = op.next().unwrap();
+            let operand = op.next().unwrap_or("").trim();
+
+            match opcode {
+                "hmvc" => halfmove = operand,
+                "fmvn" => fullmove = operand,
+                _ => (),
+            }
+        }
+
+        fields.push(halfmove);
+        fields.push(fullmove);
+
+        Board::parse_fen(&fields.join(" "), false, false)
+    }
+
+    /// Decodes a board from the compact binary format produced by Board::to_packed().
+    pub fn from_packed(packed: &[u8; 32]) -> Result<Board> {
+        let mut board = Board::default();
+
+        // The occupancy, then one nibble per occupied square.
+        let mut occ = [0; 8];
+        occ.copy_from_slice(&packed[0..8]);
+        let occ = BitBoard(u64::from_le_bytes(occ));
+
+        // The 16 bytes of nibbles hold at most 32 pieces.
+        if occ.count() > 32 {
+            return Err(Error::msg("Too many pieces in packed board."));
+        }
+
+        for (i, sq) in occ.iter_squares().enumerate() {
+            let nibble = (packed[8 + i / 2] >> (4 * (i % 2))) & 0xF;
+            let color = Color::COLORS[usize::from(nibble >> 3)];
+            let piece = *Piece::PIECES.get(usize::from(nibble & 0x7)).ok_or_else(|| Error::msg("Invalid piece in packed board."))?;
+            board.place_piece::<false, false>(color, piece, sq, &mut ());
+        }
+
+        // The flags.
+        board.state.side_to_move = Color::COLORS[usize::from(packed[24] & 1)];
+        board.state.castle_rights = CastleRights::from_raw(packed[25]).ok_or_else(|| Error::msg("Invalid castle rights in packed board."))?;
+        board.state.ep_square = match packed[26] {
+            0xFF => EnPassantSquare::None,
+            sq if sq < 64 => EnPassantSquare::Some(Square::from(sq as i8)),
+            _ => return Err(Error::msg("Invalid en passant square in packed board.")),
+        };
+        board.state.halfmove = packed[27];
+        board.ply = u16::from_le_bytes([packed[28], packed[29]]);
+
+        // Check that both sides have only one king
+        for color in Color::COLORS {
+            if !board.get_bitboard(color, Piece::King).is_one() {
+                return Err(Error::msg("Invalid number of kings on the board."));
+            }
+        }
+
+        if board.valid_castle_rights(board.state.castle_rights) != board.state.castle_rights {
+            return Err(Error::msg("Impossible castle rights in packed board."));
+        }
+
+        board.refresh_state();
+
+        Ok(board)
+    }
+
     // ================================ Accessers
 
     /// Returns the color of the side to move.
@@ -140,6 +378,12 @@ impl Board {
         self.state.checkers
     }
 
+    /// Returns true if the side to move is in check.
+    #[inline]
+    pub fn is_check(&self) -> bool {
+        self.state.checkers.not_empty()
+    }
+
     /// Returns the bitboard containing the checkers in the current position.
     #[inline]
     pub fn get_pinned(&self) -> BitBoard {
@@ -152,6 +396,41 @@ impl Board {
         self.state.halfmove
     }
 
+    /// Returns the number of plies played since the last irreversible move (a capture,
+    /// a pawn move or castling), which is the halfmove clock.
+    #[inline]
+    pub fn plies_since_irreversible(&self) -> u8 {
+        self.get_halfmove()
+    }
+
+    /// Returns the number of plies (not full moves) left before the game may be drawn
+    /// by the fifty move rule, 0 if it already may.
+    #[inline]
+    pub fn plies_until_fifty_move_draw(&self) -> u8 {
+        100u8.saturating_sub(self.get_halfmove())
+    }
+
+    /// Returns the halfmove clock: the number of plies since the last capture, pawn move or
+    /// castle, as reset by do_move(). It saturates at 255.
+    #[inline]
+    pub fn halfmove_clock(&self) -> u8 {
+        self.get_halfmove()
+    }
+
+    /// Returns true if a draw may be claimed by the fifty move rule: no capture nor pawn move
+    /// for the last 100 plies. A checkmate on the last of those plies still wins the game.
+    #[inline]
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.get_halfmove() >= 100
+    }
+
+    /// Returns true if the game is drawn by the seventy-five move rule, which the arbiter
+    /// enforces without any claim: no capture nor pawn move for the last 150 plies.
+    #[inline]
+    pub fn is_seventy_five_move_draw(&self) -> bool {
+        self.get_halfmove() >= 150
+    }
+
     /// Returns the halfmove counter.
     #[inline]
     pub fn get_ply(&self) -> u16 {
@@ -164,7 +443,15 @@ impl Board {
         self.state.castle_rights
     }
 
-    /// Returns the en passant square of the current position.
+    /// Returns the starting squares of the kings and castling rooks, which are those
+    /// of standard chess unless the board was created with Board::from_fen_960().
+    #[inline]
+    pub fn get_castle_setup(&self) -> &CastleSetup {
+        &self.castle_setup
+    }
+
+    /// Returns the en passant square of the current position: the square of the pawn that
+    /// just double pushed and may be captured en passant (not the target square, as in fen).
     #[inline]
     pub fn get_ep_square(&self) -> EnPassantSquare {
         self.state.ep_square
@@ -194,6 +481,50 @@ impl Board {
         self.state.zobrist
     }
 
+    /// The polyglot hash of the current board, as used by opening books and other engines.
+    /// It is computed from scratch with polyglot's published keys, and is unrelated to
+    /// the zobrist hash.
+    #[inline]
+    pub fn polyglot_key(&self) -> u64 {
+        book::polyglot_hash(self)
+    }
+
+    /// Iterates over the positions the game went through that are still in the history of the
+    /// board, from the oldest to the current one (included), to spot repeated positions.
+    pub fn position_history(&self) -> impl Iterator<Item = PastPosition> + '_ {
+        let first_ply = self.ply - self.prev_states.len() as u16;
+
+        self.prev_states.iter().chain(std::iter::once(&self.state)).enumerate().map(move |(i, state)| {
+            PastPosition {
+                zobrist: state.zobrist,
+                fullmove: (first_ply + i as u16) / 2 + 1,
+                side_to_move: state.side_to_move,
+            }
+        })
+    }
+
+    /// Returns the material balance in the middlegame, in centipawns, positive if white is
+    /// ahead. Maintained incrementally, see Piece::mg_value().
+    #[inline]
+    pub fn get_material_mg(&self) -> i32 {
+        self.material_mg
+    }
+
+    /// Returns the material balance in the endgame, in centipawns, positive if white is
+    /// ahead. Maintained incrementally, see Piece::eg_value().
+    #[inline]
+    pub fn get_material_eg(&self) -> i32 {
+        self.material_eg
+    }
+
+    /// Returns the game phase, from Piece::MAX_PHASE in the opening (or with more material,
+    /// after promotions) down to 0 when only kings and pawns are left. Maintained incrementally,
+    /// see Piece::phase_weight().
+    #[inline]
+    pub fn get_phase(&self) -> u8 {
+        self.phase.min(Piece::MAX_PHASE)
+    }
+
     /// Clears the history of the board, making it impossible to 
     /// undo the previous moves but freeing a bit of memory.
     #[inline]
@@ -201,6 +532,16 @@ impl Board {
         self.prev_states.clear()
     }
 
+    /// Clears the part of the history of the board that precedes the last irreversible
+    /// move (pawn move, capture or castling), making it impossible to undo the moves before
+    /// that one. Repetitions can't reach past an irreversible move, so repetition
+    /// detection and the fifty move rule remain correct.
+    #[inline]
+    pub fn truncate_history_to_last_irreversible(&mut self) {
+        let keep = usize::from(self.get_halfmove()).min(self.prev_states.len());
+        self.prev_states.drain(..self.prev_states.len() - keep);
+    }
+
     /// Returns the type of the piece present at the given square.
     /// Panics if there are no pieces there.
     #[inline]
@@ -210,7 +551,7 @@ impl Board {
 
     // ================================ Methods
 
-    // Returns the square the king of the side to move is occupying. 
+    /// Returns the square the king of that color is occupying, for either side.
     #[inline]
     pub fn king_sq(&self, color: Color) -> Square {
         let king_bb = self.get_bitboard(color, Piece::King);
@@ -218,210 +559,678 @@ impl Board {
         unsafe {king_bb.as_square_unchecked()}
     }
 
-    /// Returns the status of the current game. Must be called every turn to be accurate.
-    pub fn status(&self) -> Status {
-        let halfmoves = self.get_halfmove();
+    /// Recomputes the checkers, the pinned pieces and the zobrist key of the board
+    /// from scratch. Must be called after the board was edited manually, as those are
+    /// otherwise only updated incrementally when moves are done.
+    pub fn refresh_state(&mut self) {
+        self.state.checkers = self.checkers();
+        self.state.pinned = self.pinned();
+        self.state.zobrist = self.zobrist();
+    }
 
-        // 50 moves rule and threefold repetition.
-        if halfmoves >= 50 {
-            return Status::Draw;
-        } else if halfmoves >= 3 {
-            let repetitions = self.prev_states.iter().rev()
-                .take(usize::from(self.get_halfmove()))
-                .filter(|state| state.zobrist == self.state.zobrist)
-                .count();
+    /// Returns a copy of the board with the given side to move, the checkers, pinned pieces and
+    /// zobrist key recomputed, to ask what that side could do right now (threat detection...).
+    /// Unlike a null move, nothing is pushed to the history: the copy has none, and can't be used
+    /// to undo moves. The en passant square is cleared when the side changes. The result is only
+    /// meant for analysis and may be an illegal position, where the side not to move is in check.
+    pub fn with_side_to_move(&self, color: Color) -> Board {
+        let mut board = Board {
+            ply: self.ply,
+            bitboards: self.bitboards,
+            mailbox: self.mailbox,
+            occ: self.occ.clone(),
+            material_mg: self.material_mg,
+            material_eg: self.material_eg,
+            phase: self.phase,
+            castle_setup: self.castle_setup,
+            state: self.state.clone(),
+            prev_states: Vec::new(),
+        };
 
-            if repetitions >= 3 {
-                return Status::Draw;
-            }
+        if color != self.get_side_to_move() {
+            board.state.side_to_move = color;
+            board.state.ep_square = EnPassantSquare::None;
+            board.ply = if color == Color::Black {board.ply + 1} else {board.ply - 1};
+            board.refresh_state();
         }
 
-        // Draw by insufficient material.
-        let occ = self.get_occupancy().all();
-        match occ.count() {
-            // King versus King
-            2 => return Status::Draw,
-            3 => {
-                // King + Knight versus King or King + Bishop versus King.
-                let other = occ ^ self.get_bitboard(Color::White, Piece::King) ^ self.get_bitboard(Color::Black, Piece::King);
-                // SAFE: cardinality of other is one
-                match self.get_piece_unchecked(unsafe {other.as_square_unchecked()}) {
-                    Piece::Knight | Piece::Bishop => return Status::Draw,
-                    _ => (),
-                }
-            },
-            4 => {
-                // King + Bishop versus King + Bishop where the bishops have the same parity.
-                let others = occ ^ self.get_bitboard(Color::White, Piece::King) ^ self.get_bitboard(Color::Black, Piece::King);
-                // SAFE: cardinality of other is two
-                let sq1 = unsafe {others.as_square_unchecked()};
-                let sq2 = unsafe {others.pop_lsb().as_square_unchecked()};
-                if sq1.parity() == sq2.parity() {
-                    let (color1, piece1) = self.get_piece(sq1).unwrap();
-                    let (color2, piece2) = self.get_piece(sq2).unwrap();
-                    if color1 != color2 && piece1 == Piece::Bishop && piece2 == Piece::Bishop {
-                        return Status::Draw;
-                    }
-                }
-            },
-            _ => (),
-        }
+        board
+    }
 
-        // Stalemate, or checkmate.
-        let mut legals = Vec::new();
-        movegen::legals(self, &mut legals);
-        if legals.len() == 0 {
-            if self.get_checkers().empty() {
-                return Status::Draw;
-            } else {
-                return Status::Win(self.get_other_side());
+    /// Returns the given castle rights, without those that are impossible on this board:
+    /// a right is only kept if the king and the rook of that side are on their starting squares.
+    pub fn valid_castle_rights(&self, mut rights: CastleRights) -> CastleRights {
+        for color in Color::COLORS {
+            for side in CastleSide::SIDES {
+                let king_sq = self.castle_setup.king(color);
+                let rook_sq = self.castle_setup.rook(color, side);
+
+                if self.get_piece(king_sq) != Some((color, Piece::King)) || self.get_piece(rook_sq) != Some((color, Piece::Rook)) {
+                    rights = rights.set(color, side, false);
+                }
             }
         }
 
-        Status::Playing
+        rights
     }
 
-    /// Returns true if that pseudo-legal move is legal.
-    /// In particular, checks whether or not the move does not violate pin
-    /// (or double pin for en passant moves), or, if it is a castling move,
-    /// whether or not the squares the king traverses are safe.
-    pub fn is_legal(&self, mv: Move) -> bool {
-        let (from, to) = mv.squares();
+    /// Sets the castle rights of the board, for editing a position. The rights that are impossible
+    /// on this board (see valid_castle_rights()) are cleared: returns false if any was.
+    pub fn set_castle_rights(&mut self, rights: CastleRights) -> bool {
+        let valid = self.valid_castle_rights(rights);
 
-        if mv.is_castle() {
-            // If the move is castle, we must check that the squares the king
-            // passes are safe.
-            let can_castle = |sq1, sq2| {
-                let occ = self.get_occupancy().all();
-                (self.attackers_to(sq1, occ) | self.attackers_to(sq2, occ)).empty()
-            };
+        self.state.zobrist ^= Zobrist::from(self.state.castle_rights) ^ Zobrist::from(valid);
+        self.state.castle_rights = valid;
 
-            return match to {
-                Square::G1 => can_castle(Square::F1, Square::G1),
-                Square::G8 => can_castle(Square::F8, Square::G8),
-                Square::C1 => can_castle(Square::C1, Square::D1),
-                Square::C8 => can_castle(Square::C8, Square::D8),
-                _ => unreachable!(),
-            };
-        } else if mv.is_en_passant() {
-            // If the move is en passant, we must check that there is no double pin.
-            let ep_square = self.get_ep_square().unwrap();
-            let rank = ep_square.rank();
-            let king_sq = self.king_sq(self.get_side_to_move());
-
-            // If the king is on the same rank as the ep square (very rare).
-            if rank.contains(king_sq) {
-                let them = self.get_other_side();
-                let sliders = self.get_bitboard(them, Piece::Rook) | self.get_bitboard(them, Piece::Queen);
-
-                // For every rook or queen on that very same rank.
-                for slider_sq in (sliders & rank).iter_squares() {
-                    let between = BitBoard::between(king_sq, slider_sq);
-                    let occ = self.get_occupancy().all();
-                    
-                    // If the ep square is exactly between the king and the rook, 
-                    // and there is nothing else than the two pawns, then it is an
-                    // (incredibly rare) double pin.
-                    if between.contains(ep_square) && (between & occ).is_two() {
-                        return false;
-                    }
-                }
+        valid == rights
+    }
+
+    /// Returns the bitboard of the pieces of that color attacked by at least one piece
+    /// of the other color, whether they are defended or not.
+    pub fn threatened_pieces(&self, color: Color) -> BitBoard {
+        let mut threatened = BitBoard::EMPTY;
+
+        for sq in self.get_occupancy().colored(color).iter_squares() {
+            if self.attackers(color.invert(), sq).not_empty() {
+                threatened |= BitBoard::from(sq);
             }
-        } else if from == self.king_sq(self.get_side_to_move()) {
-            let new_occ = (self.get_occupancy().all() | BitBoard::from(to)) ^ BitBoard::from(from);
-            // If the move is done by the king, check the square it is moving to is safe.
-            return self.attackers_to(to, new_occ).empty();
         }
 
-        // Any move is valid if the piece is not pinned or if it is moving in the squares 
-        // projected from the king and onward.
-        !self.get_pinned().contains(from) || BitBoard::ray_mask(self.king_sq(self.get_side_to_move()), from).contains(to)
+        threatened
     }
 
-    /// Returns true if that random move is pseudo-legal. Only assumes that the
-    /// move was created through one of the Move type's metods.
-    pub fn is_pseudo_legal(&self, mv: Move) -> bool {
-        macro_rules! verify {($cond: expr) => {if !($cond) {return false;}}}
+    /// Returns the enemy pieces the side to move can legally capture, along with their squares,
+    /// sorted from the most valuable to the least valuable (kings excluded, they can't be captured).
+    /// A pawn that may be taken en passant is listed on it's own square.
+    pub fn capturable_pieces(&self) -> Vec<(Square, Piece)> {
+        let mut moves = Vec::new();
+        movegen::legals(self, &mut moves);
+
+        let mut pieces: Vec<_> = moves.into_iter().filter_map(|mv| if mv.is_en_passant() {
+            Some((self.get_ep_square().unwrap(), Piece::Pawn))
+        } else if mv.is_capture() {
+            Some((mv.to(), mv.get_capture()))
+        } else {
+            None
+        }).collect();
 
-        let (from, to) = mv.squares();
+        // Each piece only once, even if it can be captured in several ways.
+        pieces.sort_unstable_by_key(|&(sq, piece)| (std::cmp::Reverse(piece as u8), sq as u8));
+        pieces.dedup();
+        pieces
+    }
 
-        // Verify that the from square is occupied.
-        if let Some((color, piece)) = self.get_piece(from) {
-            // Verify it is one of our pieces.
-            verify!(color == self.get_side_to_move());
+    /// Returns the move if there is exactly one legal move in the position (a forced move),
+    /// None otherwise. The pseudo-legal moves are cheap to generate, only their legality is
+    /// checked lazily, stopping at the second legal move.
+    pub fn has_single_legal_move(&self) -> Option<Move> {
+        let mut moves = MoveList::new();
+        movegen::gen_pseudo_legals(self, |mv| moves.push(mv));
+
+        let mut legals = moves.iter().copied().filter(|&mv| self.is_legal(mv));
+        match (legals.next(), legals.next()) {
+            (Some(mv), None) => Some(mv),
+            _ => None,
+        }
+    }
 
-            // Verify to square occupied <=> move is a capture and the square 
-            // is occupied by the piece stored in the move.
-            if let Some((color, piece)) = self.get_piece(to) {
-                verify!(mv.is_capture() && color == self.get_other_side() && piece == mv.get_capture());
-            } else {
-                verify!(!mv.is_capture());
-            }
+    /// Returns the legal moves of the piece on that square, in no particular order. Empty if
+    /// the square is empty or holds a piece of the side not to move.
+    pub fn legal_moves_from(&self, sq: Square) -> Vec<Move> {
+        let mut moves = Vec::new();
+        movegen::gen_legals_from(self, sq, |mv| moves.push(mv));
+        moves
+    }
 
-            let checkers = self.get_checkers();
+    /// Returns the squares the piece on that square may legally move to, for example to highlight
+    /// them. Promotions to different pieces share their destination square.
+    pub fn legal_targets_from(&self, sq: Square) -> BitBoard {
+        let mut targets = BitBoard::EMPTY;
+        movegen::gen_legals_from(self, sq, |mv| targets |= BitBoard::from(mv.to()));
+        targets
+    }
 
-            // Special case for the king.
-            if piece == Piece::King {
-                // If the move is castling.
-                if mv.is_castle() {
-                    let can_castle = |king_sq, rook_sq, mask| {
-                        self.get_piece(rook_sq) == Some((color, Piece::Rook)) &&
-                        self.is_path_clear(king_sq, rook_sq) && 
-                        self.get_castle_rights().has(mask)
-                    };
+    /// Returns true if the side to move is checkmated: in check, without any legal move.
+    #[inline]
+    pub fn is_checkmate(&self) -> bool {
+        self.get_checkers().not_empty() && !self.has_legal_moves()
+    }
 
-                    // The king must not be in check and the path between the king and the rook must be clear.
-                    // Plus, there must be a rook on the rook square and we must possess the adequate
-                    // castling rights.
-                    return checkers.empty() && match color {
-                        Color::White => match (from, to) {
-                            (Square::E1, Square::G1) => can_castle(Square::E1, Square::H1, CastleMask::WhiteOO),
-                            (Square::E1, Square::C1) => can_castle(Square::E1, Square::A1, CastleMask::WhiteOOO),
-                            _ => return false,
-                        },
-                        Color::Black => match (from, to) {
-                            (Square::E8, Square::G8) => can_castle(Square::E8, Square::H8, CastleMask::BlackOO),
-                            (Square::E8, Square::C8) => can_castle(Square::E8, Square::A8, CastleMask::BlackOOO),
-                            _ => return false,
-                        },
-                    };
-                }
+    /// Returns true if the side to move is stalemated: not in check, without any legal move.
+    #[inline]
+    pub fn is_stalemate(&self) -> bool {
+        self.get_checkers().empty() && !self.has_legal_moves()
+    }
 
-                // Checking wether the square the king is valid for a king.
-                return attacks::king(from).contains(to);
-            } else {
-                // The move can't be a castle if the piece moving is not the king.
-                verify!(!mv.is_castle());
+    /// Returns true if that legal move gives check to the opponent, directly or by discovery
+    /// (castling and en passant included), without doing the move.
+    pub fn gives_check(&self, mv: Move) -> bool {
+        let (from, to) = mv.squares();
+        let us = self.get_side_to_move();
+        let king_sq = self.king_sq(self.get_other_side());
+
+        // The occupancy and the moved squares once the move is done.
+        let mut occ = self.get_occupancy().all() ^ BitBoard::from(from) | BitBoard::from(to);
+        let mut moved = BitBoard::from(from);
+
+        let (piece, sq) = if mv.is_castle() {
+            // In Chess960, the king and the rook may land on each other's squares.
+            let (rook_from, rook_to) = self.castle_rook_squares(to);
+            occ = self.get_occupancy().all() & !BitBoard::from(from) & !BitBoard::from(rook_from);
+            occ |= BitBoard::from(to) | BitBoard::from(rook_to);
+            moved |= BitBoard::from(rook_from);
+            (Piece::Rook, rook_to)
+        } else if mv.is_promote() {
+            (mv.get_promote(), to)
+        } else {
+            if mv.is_en_passant() {
+                occ ^= BitBoard::from(self.get_ep_square().unwrap());
+            }
+            (self.get_piece_unchecked(from), to)
+        };
 
-                // If there are any checkers.
-                if checkers.not_empty() {
-                    // Two checkers, the piece moving must be the king.
-                    verify!(!checkers.more_than_one());
+        // Direct check, by the piece that moved.
+        let direct = match piece {
+            Piece::Pawn   => attacks::pawn(us, sq),
+            Piece::Knight => attacks::knight(sq),
+            Piece::Bishop => attacks::bishop(sq, occ),
+            Piece::Rook   => attacks::rook(sq, occ),
+            Piece::Queen  => attacks::queen(sq, occ),
+            Piece::King   => BitBoard::EMPTY,
+        };
 
-                    // One checker, the piece moving must either block or capture the enemy piece.
-                    // SAFE: there is always a king on the board
-                    let checker = unsafe {checkers.as_square_unchecked()};
-                    let blocking_zone = BitBoard::between(self.king_sq(self.get_side_to_move()), checker);
-                    verify!((blocking_zone | checkers).contains(to));
-                }
-            }
+        if direct.contains(king_sq) {
+            return true;
+        }
 
-            // Special case for pawns.
-            if piece == Piece::Pawn {
-                if mv.is_en_passant() {
-                    // There must be an en passant square.
-                    verify!(self.get_ep_square().is_some());
-                    let ep_square = self.get_ep_square().unwrap();
+        // Discovered check, by a slider that was behind the moved pieces.
+        let queens = self.get_bitboard(us, Piece::Queen);
+        let diagonal = (self.get_bitboard(us, Piece::Bishop) | queens) & !moved;
+        let straight = (self.get_bitboard(us, Piece::Rook) | queens) & !moved;
 
-                    // The ep square must be between the move's squares.
-                    // SAFE: 0 <= to.x() < 8 and 0 <= from.y() < 8
-                    verify!(ep_square == unsafe {Square::from_xy_unchecked(to.x(), from.y())});
+        (attacks::bishop(king_sq, occ) & diagonal | attacks::rook(king_sq, occ) & straight).not_empty()
+    }
 
-                    // It is a valid pawn attack too.
-                    return attacks::pawn(color, from).contains(to);
-                } else {
-                    // If the move is a promotion, it must go to the first or last rank.
-                    verify!(BitBoard::promote_rank(self.get_side_to_move()).contains(from) || !mv.is_promote());
+    /// Returns the static exchange evaluation of that pseudo-legal move, in centipawns: the
+    /// material won or lost once both sides have captured on its destination square as long as
+    /// it pays off for them, least valuable attackers first. Sliders uncovered by a capture join
+    /// in. Pins, and promotions after the first capture, are not taken into account.
+    pub fn see(&self, mv: Move) -> i32 {
+        // Kings are worth more than anything, so that they only capture last.
+        let value = |piece: Piece| if piece == Piece::King {20000} else {piece.mg_value()};
+
+        let (from, to) = mv.squares();
+        let mut occ = self.get_occupancy().all() ^ BitBoard::from(from);
+
+        // The material gained by the side capturing at each step, assuming it is recaptured.
+        let mut gain = [0; 32];
+        gain[0] = if mv.is_en_passant() {
+            occ ^= BitBoard::from(self.get_ep_square().unwrap());
+            value(Piece::Pawn)
+        } else if mv.is_capture() {
+            value(mv.get_capture())
+        } else {
+            0
+        };
+
+        // The piece standing on the square, to be captured next.
+        let mut target = self.get_piece_unchecked(from);
+        if mv.is_promote() {
+            target = mv.get_promote();
+            gain[0] += value(target) - value(Piece::Pawn);
+        }
+
+        let mut depth = 0;
+        let mut side = self.get_other_side();
+
+        loop {
+            let attackers = self.all_attackers_to(to, occ) & occ;
+            let ours = attackers & self.get_occupancy().colored(side);
+
+            // The least valuable attacker, the king only if the square is not defended anymore.
+            let attacker = Piece::PIECES.iter()
+                .map(|&piece| (piece, ours & self.get_bitboard(side, piece)))
+                .find(|(_, bb)| bb.not_empty());
+
+            let (piece, bb) = match attacker {
+                Some((Piece::King, _)) if (attackers & !ours).not_empty() => break,
+                Some(attacker) => attacker,
+                None => break,
+            };
+
+            depth += 1;
+            gain[depth] = value(target) - gain[depth - 1];
+
+            // SAFE: bb is not empty.
+            occ ^= BitBoard::from(unsafe {bb.as_square_unchecked()});
+            target = piece;
+            side = side.invert();
+        }
+
+        // Each side may stop capturing when it doesn't pay off.
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+
+        gain[0]
+    }
+
+    /// Returns all the legal moves of the position that give check.
+    pub fn checking_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        movegen::gen_checking_moves(self, |mv| moves.push(mv));
+        moves
+    }
+
+    /// Returns true if that legal move checkmates the opponent (stalemating is not checkmating).
+    /// The move is done, the replies of the opponent generated, then the move is undone, so this
+    /// is way more expensive than a simple check test: use it sparingly, e.g. on checking moves only.
+    pub fn gives_checkmate(&mut self, mv: Move) -> bool {
+        self.do_move(mv);
+
+        let mut mated = self.get_checkers().not_empty();
+        if mated {
+            movegen::gen_legals(self, |_| mated = false);
+        }
+
+        self.undo_move(mv);
+        mated
+    }
+
+    /// Returns the squares a piece of the side to move standing on sq may move to without
+    /// exposing its king: the ray from the king through sq if that piece is pinned, or
+    /// the full board (BitBoard::FULL) if it is not.
+    #[inline]
+    pub fn pin_mask(&self, sq: Square) -> BitBoard {
+        if self.get_pinned().contains(sq) {
+            BitBoard::ray_mask(self.king_sq(self.get_side_to_move()), sq)
+        } else {
+            BitBoard::FULL
+        }
+    }
+
+    /// Returns false if the side that just moved (the side not to move) is in check,
+    /// which is an impossible position.
+    #[inline]
+    pub fn is_position_legal(&self) -> bool {
+        self.attackers(self.get_side_to_move(), self.king_sq(self.get_other_side())).empty()
+    }
+
+    /// Returns the bitboard of the pieces of that color attacking the given square,
+    /// with the current occupancy. Does not take en passant into account.
+    #[inline]
+    pub fn attackers(&self, color: Color, sq: Square) -> BitBoard {
+        let occ = self.get_occupancy().all();
+        let queens = self.get_bitboard(color, Piece::Queen);
+
+        attacks::pawn(color.invert(), sq) & self.get_bitboard(color, Piece::Pawn)
+        | attacks::rook(sq, occ) & (self.get_bitboard(color, Piece::Rook) | queens)
+        | attacks::knight(sq) & self.get_bitboard(color, Piece::Knight)
+        | attacks::bishop(sq, occ) & (self.get_bitboard(color, Piece::Bishop) | queens)
+        | attacks::king(sq) & self.get_bitboard(color, Piece::King)
+    }
+
+    /// Returns the number of white and black pieces attacking the given square,
+    /// in that order.
+    #[inline]
+    pub fn square_control(&self, sq: Square) -> (u32, u32) {
+        (
+            self.attackers(Color::White, sq).count() as u32,
+            self.attackers(Color::Black, sq).count() as u32,
+        )
+    }
+
+    /// Returns, for each square, the number of white pieces attacking it minus the
+    /// number of black pieces attacking it. Indexed like the squares.
+    pub fn control_map(&self) -> [i8; 64] {
+        let mut map = [0; 64];
+
+        for &sq in &Square::SQUARES {
+            let (white, black) = self.square_control(sq);
+            map[usize::from(sq)] = white as i8 - black as i8;
+        }
+
+        map
+    }
+
+    /// Returns the pawns of that color with no enemy pawn in front of them, on
+    /// their file or on the adjacent files.
+    pub fn passed_pawns(&self, color: Color) -> BitBoard {
+        let enemy_pawns = self.get_bitboard(color.invert(), Piece::Pawn);
+        let mut passed = BitBoard::EMPTY;
+
+        for sq in self.get_bitboard(color, Piece::Pawn).iter_squares() {
+            if ((pawns::front_span(color, sq) | pawns::attack_span(color, sq)) & enemy_pawns).empty() {
+                passed |= BitBoard::from(sq);
+            }
+        }
+
+        passed
+    }
+
+    /// Returns the pawns of that color with another pawn of that color in front of them, on
+    /// their file: all pawns of a file but the most advanced one.
+    pub fn doubled_pawns(&self, color: Color) -> BitBoard {
+        let pawns = self.get_bitboard(color, Piece::Pawn);
+        let mut doubled = BitBoard::EMPTY;
+
+        for sq in pawns.iter_squares() {
+            if (pawns::front_span(color, sq) & pawns).not_empty() {
+                doubled |= BitBoard::from(sq);
+            }
+        }
+
+        doubled
+    }
+
+    /// Returns the pawns of that color without any pawn of that color on the adjacent files.
+    pub fn isolated_pawns(&self, color: Color) -> BitBoard {
+        let pawns = self.get_bitboard(color, Piece::Pawn);
+
+        // The files with pawns, then the files next to them.
+        let files = pawns::front_fill(Color::White, pawns) | pawns::front_fill(Color::Black, pawns);
+        let adjacent = (files << 1 & !BitBoard::FILE_A) | (files >> 1 & !BitBoard::FILE_H);
+
+        pawns & !adjacent
+    }
+
+    /// Returns a penalty, in centipawns, for the missing or advanced pawns of that color
+    /// sheltering its king, on the king's file and on the adjacent ones.
+    pub fn pawn_shelter(&self, color: Color) -> i32 {
+        // The penalty for the closest pawn of a file, indexed by its distance to the king's rank.
+        const PENALTIES: [i32; 8] = [0, 0, 10, 20, 25, 25, 25, 25];
+        // The penalty for a file without pawns in front of the king.
+        const MISSING: i32 = 36;
+
+        let pawns = self.get_bitboard(color, Piece::Pawn);
+
+        self.king_files_fronts(color).map(|(king_y, front)| {
+            (front & pawns).iter_squares()
+                .map(|sq| PENALTIES[usize::from((sq.y() - king_y).unsigned_abs())])
+                .min()
+                .unwrap_or(MISSING)
+        }).sum()
+    }
+
+    /// Returns a penalty, in centipawns, for the enemy pawns advancing towards the king of
+    /// that color, on the king's file and on the adjacent ones.
+    pub fn pawn_storm(&self, color: Color) -> i32 {
+        // The penalty for the closest enemy pawn of a file, indexed by its distance to the king's rank.
+        const PENALTIES: [i32; 8] = [0, 10, 30, 15, 5, 0, 0, 0];
+
+        let pawns = self.get_bitboard(color.invert(), Piece::Pawn);
+
+        self.king_files_fronts(color).map(|(king_y, front)| {
+            (front & pawns).iter_squares()
+                .map(|sq| PENALTIES[usize::from((sq.y() - king_y).unsigned_abs())])
+                .max()
+                .unwrap_or(0)
+        }).sum()
+    }
+
+    /// Returns the pressure on the ring of the defending king (the squares around it): the number
+    /// of attacks of the opponent on each of those squares, summed. A cheap king safety signal.
+    pub fn king_ring_pressure(&self, defending: Color) -> u32 {
+        let attacking = defending.invert();
+
+        attacks::king(self.king_sq(defending)).iter_squares()
+            .map(|sq| self.attackers(attacking, sq).count() as u32)
+            .sum()
+    }
+
+    /// Returns true if the king of that color has a back rank weakness: it stands on its back
+    /// rank, every square in front of it is taken by its own pieces or attacked by the enemy (no
+    /// luft), and an enemy rook or queen sees the back rank. This is a heuristic flag meant to
+    /// surface the motif, not a proof of mate: the back rank may still be defended.
+    pub fn back_rank_weakness(&self, color: Color) -> bool {
+        let them = color.invert();
+        let king_sq = self.king_sq(color);
+        let back_rank = if color == Color::White {BitBoard::RANK_1} else {BitBoard::RANK_8};
+
+        if !back_rank.contains(king_sq) {
+            return false;
+        }
+
+        // The squares the king could flee to, off the back rank.
+        let own = self.get_occupancy().colored(color);
+        let boxed = (attacks::king(king_sq) & !back_rank).iter_squares()
+            .all(|sq| own.contains(sq) || self.attackers(them, sq).not_empty());
+
+        if !boxed {
+            return false;
+        }
+
+        let occ = self.get_occupancy().all();
+        let heavy = self.get_bitboard(them, Piece::Rook) | self.get_bitboard(them, Piece::Queen);
+
+        heavy.iter_squares().any(|sq| (attacks::rook(sq, occ) & back_rank).not_empty())
+    }
+
+    /// Returns the number of rooks of that color standing on their relative seventh rank.
+    #[inline]
+    pub fn rooks_on_seventh(&self, color: Color) -> u32 {
+        (self.get_bitboard(color, Piece::Rook) & BitBoard::promote_rank(color)).count() as u32
+    }
+
+    /// Returns true if two rooks of that color are defending each other,
+    /// on the same rank or file with nothing in between.
+    #[inline]
+    pub fn connected_rooks(&self, color: Color) -> bool {
+        let rooks = self.get_bitboard(color, Piece::Rook);
+
+        rooks.iter_squares().any(|from| {
+            rooks.iter_squares().any(|to| {
+                from != to && (from.x() == to.x() || from.y() == to.y()) && self.is_path_clear(from, to)
+            })
+        })
+    }
+
+    /// Returns true if that color has at least two bishops standing on opposite-colored squares.
+    #[inline]
+    pub fn has_bishop_pair(&self, color: Color) -> bool {
+        let mut bishops = self.get_bitboard(color, Piece::Bishop).iter_squares();
+
+        match bishops.next() {
+            Some(first) => bishops.any(|sq| !sq.same_color(first)),
+            None => false,
+        }
+    }
+
+    /// Returns how many times the current position occurred in the game, this one included,
+    /// as far as the history of the board goes. Only the positions since the last irreversible
+    /// move, with the same side to move, are looked at.
+    pub fn repetition_count(&self) -> usize {
+        let reachable = usize::from(self.get_halfmove()).min(self.prev_states.len());
+
+        1 + self.prev_states.iter().rev()
+            .take(reachable)
+            .skip(1)
+            .step_by(2)
+            .filter(|state| state.zobrist == self.state.zobrist)
+            .count()
+    }
+
+    /// Returns true if the current position occurred at least three times, so that
+    /// a draw may be claimed.
+    #[inline]
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+
+    /// Returns the status of the current game. Must be called every turn to be accurate.
+    pub fn status(&self) -> Status {
+        let has_legal_moves = self.has_legal_moves();
+
+        // Checkmate, even when delivered on the move that completes the 50 moves.
+        if !has_legal_moves && self.get_checkers().not_empty() {
+            return Status::Win(self.get_other_side());
+        }
+
+        // 50 moves rule and threefold repetition.
+        if self.is_fifty_move_draw() || self.is_threefold_repetition() {
+            return Status::Draw;
+        }
+
+        // Draw by insufficient material.
+        let occ = self.get_occupancy().all();
+        match occ.count() {
+            // King versus King
+            2 => return Status::Draw,
+            3 => {
+                // King + Knight versus King or King + Bishop versus King.
+                let other = occ ^ self.get_bitboard(Color::White, Piece::King) ^ self.get_bitboard(Color::Black, Piece::King);
+                // SAFE: cardinality of other is one
+                match self.get_piece_unchecked(unsafe {other.as_square_unchecked()}) {
+                    Piece::Knight | Piece::Bishop => return Status::Draw,
+                    _ => (),
+                }
+            },
+            4 => {
+                // King + Bishop versus King + Bishop where the bishops have the same parity.
+                let others = occ ^ self.get_bitboard(Color::White, Piece::King) ^ self.get_bitboard(Color::Black, Piece::King);
+                // SAFE: cardinality of other is two
+                let sq1 = unsafe {others.as_square_unchecked()};
+                let sq2 = unsafe {others.pop_lsb().as_square_unchecked()};
+                if sq1.same_color(sq2) {
+                    let (color1, piece1) = self.get_piece(sq1).unwrap();
+                    let (color2, piece2) = self.get_piece(sq2).unwrap();
+                    if color1 != color2 && piece1 == Piece::Bishop && piece2 == Piece::Bishop {
+                        return Status::Draw;
+                    }
+                }
+            },
+            _ => (),
+        }
+
+        // Stalemate.
+        if !has_legal_moves {
+            return Status::Draw;
+        }
+
+        Status::Playing
+    }
+
+    /// Returns true if that pseudo-legal move is legal.
+    /// In particular, checks whether or not the move does not violate pin
+    /// (or double pin for en passant moves), or, if it is a castling move,
+    /// whether or not the squares the king traverses are safe.
+    pub fn is_legal(&self, mv: Move) -> bool {
+        let (from, to) = mv.squares();
+
+        if mv.is_castle() {
+            // If the move is castle, we must check that the squares the king passes are safe.
+            // Both the king and the rook leave their squares: in Chess960, the rook may have
+            // been shielding the king's destination.
+            let (rook_from, _) = self.castle_rook_squares(to);
+            let occ = self.get_occupancy().all() & !BitBoard::from(from) & !BitBoard::from(rook_from);
+            let path = BitBoard::between(from, to) | BitBoard::from(to);
+
+            return path.iter_squares().all(|sq| self.attackers_to(sq, occ).empty());
+        } else if mv.is_en_passant() {
+            // En passant moves remove two pieces at once, they have their own function.
+            return self.is_en_passant_legal(mv);
+        } else if from == self.king_sq(self.get_side_to_move()) {
+            let new_occ = (self.get_occupancy().all() | BitBoard::from(to)) ^ BitBoard::from(from);
+            // If the move is done by the king, check the square it is moving to is safe.
+            return self.attackers_to(to, new_occ).empty();
+        }
+
+        // Any move is valid if the piece is not pinned or if it is moving in the squares 
+        // projected from the king and onward.
+        self.pin_mask(from).contains(to)
+    }
+
+    /// Returns true if that en passant move is legal, assuming it is pseudo-legal: our king
+    /// must not be attacked once both pawns have left their squares and ours landed on the
+    /// target square. This covers the capturing pawn being pinned, the (incredibly rare)
+    /// double pin of both pawns along their rank, and the capture of a pawn giving check.
+    #[inline]
+    pub fn is_en_passant_legal(&self, mv: Move) -> bool {
+        let (from, to) = mv.squares();
+        let captured = BitBoard::from(self.get_ep_square().unwrap());
+        let occ = (self.get_occupancy().all() ^ BitBoard::from(from) ^ captured) | BitBoard::from(to);
+
+        // The captured pawn is still in the bitboards, ignore it.
+        (self.attackers_to(self.king_sq(self.get_side_to_move()), occ) & !captured).empty()
+    }
+
+    /// Returns true if that random move is pseudo-legal. Only assumes that the
+    /// move was created through one of the Move type's metods.
+    pub fn is_pseudo_legal(&self, mv: Move) -> bool {
+        macro_rules! verify {($cond: expr) => {if !($cond) {return false;}}}
+
+        let (from, to) = mv.squares();
+
+        // Verify that the from square is occupied.
+        if let Some((color, piece)) = self.get_piece(from) {
+            // Verify it is one of our pieces.
+            verify!(color == self.get_side_to_move());
+
+            // If the move is castling: the king must not be in check, we must possess the adequate
+            // castling rights, the king and the rook must be on their starting squares and the
+            // squares they go through must be clear. In Chess960, the king may land on the rook.
+            if mv.is_castle() {
+                return match CastleSide::from_king_to(to) {
+                    Some((castle_color, side)) => {
+                        castle_color == color &&
+                        piece == Piece::King &&
+                        from == self.castle_setup.king(color) &&
+                        self.get_checkers().empty() &&
+                        self.get_castle_rights().can(color, side) &&
+                        self.get_piece(self.castle_setup.rook(color, side)) == Some((color, Piece::Rook)) &&
+                        self.is_castle_path_clear(color, side)
+                    },
+                    None => false,
+                };
+            }
+
+            // Verify to square occupied <=> move is a capture and the square 
+            // is occupied by the piece stored in the move.
+            if let Some((color, piece)) = self.get_piece(to) {
+                verify!(mv.is_capture() && color == self.get_other_side() && piece == mv.get_capture());
+            } else {
+                verify!(!mv.is_capture());
+            }
+
+            let checkers = self.get_checkers();
+
+            // Special case for the king.
+            if piece == Piece::King {
+                // Checking wether the square the king is valid for a king.
+                return attacks::king(from).contains(to);
+            } else {
+                // If there are any checkers.
+                if checkers.not_empty() {
+                    // Two checkers, the piece moving must be the king.
+                    verify!(!checkers.more_than_one());
+
+                    // One checker, the piece moving must either block or capture the enemy piece.
+                    // SAFE: there is always a king on the board
+                    let checker = unsafe {checkers.as_square_unchecked()};
+                    let blocking_zone = BitBoard::between(self.king_sq(self.get_side_to_move()), checker);
+                    // En passant captures may take a checking pawn without landing on it's square, they
+                    // are fully verified by is_legal().
+                    verify!(mv.is_en_passant() || (blocking_zone | checkers).contains(to));
+                }
+            }
+
+            // Special case for pawns.
+            if piece == Piece::Pawn {
+                if mv.is_en_passant() {
+                    // There must be an en passant square.
+                    verify!(self.get_ep_square().is_some());
+                    let ep_square = self.get_ep_square().unwrap();
+
+                    // The ep square must be between the move's squares.
+                    // SAFE: 0 <= to.x() < 8 and 0 <= from.y() < 8
+                    verify!(ep_square == unsafe {Square::from_xy_unchecked(to.x(), from.y())});
+
+                    // It is a valid pawn attack too.
+                    return attacks::pawn(color, from).contains(to);
+                } else {
+                    // The move is a promotion if and only if it goes to the first or last rank.
+                    verify!(BitBoard::promote_rank(self.get_side_to_move()).contains(from) == mv.is_promote());
 
                     // Verify that the move is legal for a pawn.
                     if mv.is_capture() {
@@ -458,6 +1267,13 @@ impl Board {
     /// Do the move without checking anything about it's legality.
     #[inline]
     pub fn do_move(&mut self, mv: Move) {
+        self.do_move_with(mv, &mut ());
+    }
+
+    /// Do the move without checking anything about it's legality, notifying the
+    /// listener of every piece placed, removed or moved.
+    #[inline]
+    pub fn do_move_with<L: EvalListener>(&mut self, mv: Move, listener: &mut L) {
         // Clone the previous state to store it later.
         let old_state = self.state.clone();
 
@@ -472,46 +1288,43 @@ impl Board {
         // Invert the side to move.
         self.state.side_to_move = self.get_other_side();
 
-        // Extract base move infos and remove piece from it's starting position.
+        // Extract base move infos.
         let (from, to) = mv.squares();
-        let (color, mut piece) = self.remove_piece::<true>(from);
+        let (color, piece) = self.get_piece(from).unwrap();
 
         // Determine if the move is reversible or not.
         let reversible = mv.is_quiet() && piece != Piece::Pawn;
 
         if mv.is_castle() {
-            // If the move is castling, move the rook as well.
-            match to {
-                Square::G1 => self.displace_piece::<true>(Square::H1, Square::F1),
-                Square::G8 => self.displace_piece::<true>(Square::H8, Square::F8),
-                Square::C1 => self.displace_piece::<true>(Square::A1, Square::D1),
-                Square::C8 => self.displace_piece::<true>(Square::A8, Square::D8),
-                _ => unreachable!(),
-            };
+            // If the move is castling, move the rook as well. In Chess960, the king and the
+            // rook may land on each other's squares: lift the king before moving the rook.
+            let (rook_from, rook_to) = self.castle_rook_squares(to);
+            self.remove_piece::<true, true>(from, listener);
+            self.displace_piece::<true, true>(rook_from, rook_to, listener);
         } else if mv.is_en_passant() {
             // If the move is en passant, remove the pawn at the en passant square.
-            self.remove_piece::<true>(self.get_ep_square().unwrap());
-        } else {
+            self.remove_piece::<true, true>(self.get_ep_square().unwrap(), listener);
+        } else if mv.is_capture() {
             // If the move is a capture, remove the enemy piece from the destination square.
-            if mv.is_capture() {
-                self.remove_piece::<true>(to);
-            }
-    
-            // If the move is a promotion, set the piece to be the promotion.
-            if mv.is_promote() {
-                piece = mv.get_promote();
-            }
+            self.remove_piece::<true, true>(to, listener);
         }
 
-        // Finally, place the piece at it's destination.
-        self.place_piece::<true>(color, piece, to);
+        // Finally, move the piece to it's destination, replacing it if it's a promotion.
+        if mv.is_castle() {
+            self.place_piece::<true, true>(color, Piece::King, to, listener);
+        } else if mv.is_promote() {
+            self.remove_piece::<true, true>(from, listener);
+            self.place_piece::<true, true>(color, mv.get_promote(), to, listener);
+        } else {
+            self.displace_piece::<true, true>(from, to, listener);
+        }
 
         // Determine checkers and pinned bitboard.
         self.state.checkers = self.checkers();
         self.state.pinned = self.pinned();
 
         // Update castling rights.
-        self.state.castle_rights.update(from, to);
+        self.state.castle_rights.update_with(&self.castle_setup, from, to);
         self.state.zobrist ^= Zobrist::from(self.state.castle_rights);
 
         // Update en passant square.
@@ -525,18 +1338,58 @@ impl Board {
 
         // Update the halfmove clock.
         if reversible {
-            self.state.halfmove += 1;
+            self.state.halfmove = self.state.halfmove.saturating_add(1);
         } else {
             self.state.halfmove = 0;
         }
 
         // Invert zobrist since we change side.
         self.state.zobrist = !self.state.zobrist;
+
+        debug_assert_eq!((self.material_mg, self.material_eg, self.phase), self.material());
+    }
+
+    /// Returns true if doing that move would reset the halfmove clock: pawn moves,
+    /// captures and castling. Assumes the move is pseudo-legal.
+    #[inline]
+    pub fn move_resets_clock(&self, mv: Move) -> bool {
+        !mv.is_quiet() || self.get_piece_unchecked(mv.from()) == Piece::Pawn
+    }
+
+    /// Do the move without checking anything about it's legality, and
+    /// returns the informations about what the move changed.
+    #[inline]
+    pub fn do_move_info(&mut self, mv: Move) -> MoveInfo {
+        let captured = if mv.is_en_passant() {
+            Some(Piece::Pawn)
+        } else if mv.is_capture() {
+            Some(mv.get_capture())
+        } else {
+            None
+        };
+
+        let info = MoveInfo {
+            irreversible: self.move_resets_clock(mv),
+            captured,
+            castle_rights: self.get_castle_rights(),
+            ep_square: self.get_ep_square(),
+            halfmove: self.get_halfmove(),
+        };
+
+        self.do_move(mv);
+        info
     }
 
     /// Undoes the move, reverting the board to it's previous state.
     #[inline]
     pub fn undo_move(&mut self, mv: Move) {
+        self.undo_move_with(mv, &mut ());
+    }
+
+    /// Undoes the move, reverting the board to it's previous state and notifying
+    /// the listener of every piece placed, removed or moved.
+    #[inline]
+    pub fn undo_move_with<L: EvalListener>(&mut self, mv: Move, listener: &mut L) {
         // Them color.
         let them = self.get_side_to_move();
 
@@ -544,35 +1397,34 @@ impl Board {
         self.state = self.prev_states.pop().unwrap();
         self.ply -= 1;
 
-        // Extract basic move info and remove the piece from it's destination.
+        // Extract basic move info and move the piece back to it's starting square,
+        // as a pawn if the move was a promotion.
         let (from, to) = mv.squares();
-        let (color, mut piece) = self.remove_piece::<false>(to);
+        
+        if mv.is_castle() {
+            // The king is placed back after the rook, see do_move_with().
+            self.remove_piece::<false, true>(to, listener);
+        } else if mv.is_promote() {
+            let (color, _) = self.remove_piece::<false, true>(to, listener);
+            self.place_piece::<false, true>(color, Piece::Pawn, from, listener);
+        } else {
+            self.displace_piece::<false, true>(to, from, listener);
+        }
 
         if mv.is_castle() {
             // If the move was castling, move the rook back as well.
-            match to {
-                Square::G1 => self.displace_piece::<true>(Square::F1, Square::H1),
-                Square::G8 => self.displace_piece::<true>(Square::F8, Square::H8),
-                Square::C1 => self.displace_piece::<true>(Square::D1, Square::A1),
-                Square::C8 => self.displace_piece::<true>(Square::D8, Square::A8),
-                _ => unreachable!(),
-            };
+            let (rook_from, rook_to) = self.castle_rook_squares(to);
+            self.displace_piece::<false, true>(rook_to, rook_from, listener);
+            self.place_piece::<false, true>(self.get_side_to_move(), Piece::King, from, listener);
         } else if mv.is_en_passant() {
             // If the move was en passant, place the enemy pawn back as well.
-            self.place_piece::<false>(them, Piece::Pawn, self.get_ep_square().unwrap());
-        } else {
+            self.place_piece::<false, true>(them, Piece::Pawn, self.get_ep_square().unwrap(), listener);
+        } else if mv.is_capture() {
             // If the move was a capture, replace the taken enemy piece in it's place.
-            if mv.is_capture() {
-                self.place_piece::<false>(them, mv.get_capture(), to);
-            }
-    
-            // If the move was a promotion, the original piece was a pawn.
-            if mv.is_promote() {
-                piece = Piece::Pawn;
-            }
+            self.place_piece::<false, true>(them, mv.get_capture(), to, listener);
         }
 
-        self.place_piece::<false>(color, piece, from);
+        debug_assert_eq!((self.material_mg, self.material_eg, self.phase), self.material());
     }
 
     /// Performs a null move (pass). Intended only for the engine to perform null-move pruning.
@@ -581,9 +1433,8 @@ impl Board {
         // Clone the previous state to store it later.
         let old_state = self.state.clone();
 
-        // Undo the zobrist hashing of the ep square and castle rights.
+        // Undo the zobrist hashing of the ep square, castle rights are left untouched.
         self.state.zobrist ^= Zobrist::from(old_state.ep_square);
-        self.state.zobrist ^= Zobrist::from(old_state.castle_rights);
 
         // Store previous state and increment fullmove counter.
         self.prev_states.push(old_state);
@@ -600,7 +1451,7 @@ impl Board {
         self.state.ep_square = EnPassantSquare::None;
 
         // Update the halfmove clock.
-        self.state.halfmove += 1;
+        self.state.halfmove = self.state.halfmove.saturating_add(1);
 
         // Invert zobrist since we change side.
         self.state.zobrist = !self.state.zobrist;
@@ -645,9 +1496,48 @@ impl Board {
         false
     }
 
-    /// Tries to build a move from the given parameters. Returns an error if the move is illegal.
+    /// Returns true if moving the piece of the side to move standing on from to the to square
+    /// would capture something in this position, en passant included. Does not check that the
+    /// move is legal, only that from holds a piece of the side to move.
+    pub fn is_capture_move(&self, from: Square, to: Square) -> bool {
+        let us = self.get_side_to_move();
+
+        match self.get_piece(from) {
+            Some((color, _)) if color != us => false,
+            None => false,
+            Some((_, piece)) => match self.get_piece(to) {
+                Some((color, _)) => color != us,
+                None => {
+                    piece == Piece::Pawn &&
+                    from.x() != to.x() &&
+                    self.get_ep_square() == EnPassantSquare::Some(Square::from((to.x(), from.y())))
+                },
+            },
+        }
+    }
+
+    /// Returns true if moving the piece of the side to move standing on from to the to square
+    /// would be a quiet move in this position: neither a capture nor a promotion. Does not check
+    /// that the move is legal, only that from holds a piece of the side to move.
+    pub fn is_quiet_move(&self, from: Square, to: Square) -> bool {
+        match self.get_piece(from) {
+            Some((color, piece)) if color == self.get_side_to_move() => {
+                let promotes = piece == Piece::Pawn && (to.y() == 0 || to.y() == 7);
+                !promotes && !self.is_capture_move(from, to)
+            },
+            _ => false,
+        }
+    }
+
+    /// Tries to build a move from the given parameters, inferring its kind (capture,
+    /// en passant, castling, double push) from the position. If the move is illegal,
+    /// returns an error wrapping the IllegalMoveReason describing why.
     pub fn make_move(&self, from: Square, to: Square, maybe_promote: Option<Piece>) -> Result<Move> {
         let mv = if let Some(promote) = maybe_promote {
+            if !Piece::PROMOTES.contains(&promote) {
+                return Err(IllegalMoveReason::InvalidPromotion.into());
+            }
+
             if let Some((_, capture)) = self.get_piece(to) {
                 Move::promote_capture(from, to, capture, promote)
             } else {
@@ -668,9 +1558,18 @@ impl Board {
                         Move::en_passant(from, to)
                     }
                 },
-                Some((_, Piece::King)) => {
-                    if (to.x() - from.x()).abs() == 2 {
-                        Move::castle(from, to)
+                Some((color, Piece::King)) => {
+                    // In Chess960, castling is written as the king taking it's own rook.
+                    let castle_side = CastleSide::SIDES.iter()
+                        .find(|&&side| self.castle_setup.rook(color, side) == to && self.get_piece(to) == Some((color, Piece::Rook)));
+
+                    if let Some(&side) = castle_side.filter(|_| from == self.castle_setup.king(color)) {
+                        self.castle_move(color, side)
+                    } else if (to.x() - from.x()).abs() == 2 {
+                        match CastleSide::from_king_to(to) {
+                            Some((castle_color, side)) if castle_color == color && from == self.castle_setup.king(color) => self.castle_move(color, side),
+                            _ => Move::castle(from, to),
+                        }
                     } else if let Some((_, capture)) = self.get_piece(to) {
                         Move::capture(from, to, capture)
                     } else {
@@ -690,7 +1589,7 @@ impl Board {
         if self.is_pseudo_legal(mv) && self.is_legal(mv) {
             Ok(mv)
         } else {
-            Err(Error::msg("Move is invalid in this context."))
+            Err(self.illegal_reason(mv).into())
         }
     }
 
@@ -718,6 +1617,207 @@ impl Board {
         }
     }
 
+    /// Returns the standard algebraic notation (SAN) of that legal move, as found in pgn files:
+    /// Nbd7, exd5, O-O, e8=Q+, Rxe4#... The origin of the piece is given by its file, rank, or
+    /// both, when other pieces of the same kind could go to the same square.
+    pub fn move_to_san(&self, mv: Move) -> String {
+        let (from, to) = mv.squares();
+        let piece = self.get_piece_unchecked(from);
+
+        let mut san = if mv.is_castle() {
+            match CastleSide::from_king_to(to) {
+                Some((_, CastleSide::King)) => String::from("O-O"),
+                _ => String::from("O-O-O"),
+            }
+        } else if piece == Piece::Pawn {
+            let mut san = String::new();
+            if mv.is_capture() || mv.is_en_passant() {
+                san.push((b'a' + from.x() as u8) as char);
+                san.push('x');
+            }
+            san.push_str(&to.to_string());
+            if mv.is_promote() {
+                san.push('=');
+                san.push(mv.get_promote().as_char(Color::White));
+            }
+            san
+        } else {
+            // The other pieces of the same kind that may go to the same square.
+            let mut others = BitBoard::EMPTY;
+            movegen::gen_legals(self, |other| {
+                if other.to() == to && other.from() != from && !other.is_castle() && self.get_piece_unchecked(other.from()) == piece {
+                    others |= BitBoard::from(other.from());
+                }
+            });
+
+            let mut san = String::new();
+            san.push(piece.as_char(Color::White));
+            if others.not_empty() {
+                let square = from.to_string();
+                if others.iter_squares().all(|sq| sq.x() != from.x()) {
+                    san.push_str(&square[0..1]);
+                } else if (others & from.rank()).empty() {
+                    san.push_str(&square[1..2]);
+                } else {
+                    san.push_str(&square);
+                }
+            }
+            if mv.is_capture() {
+                san.push('x');
+            }
+            san.push_str(&to.to_string());
+            san
+        };
+
+        if self.gives_check(mv) {
+            san.push(if self.clone().gives_checkmate(mv) {'#'} else {'+'});
+        }
+
+        san
+    }
+
+    /// Parses a legal move in standard algebraic notation (SAN), as found in pgn files: Nbd2,
+    /// R1e2, exd5, e8=Q, O-O... Check and checkmate marks and annotations (+, #, !, ?) are
+    /// ignored. Returns an error if no legal move, or more than one, matches.
+    pub fn parse_san(&self, s: &str) -> Result<Move> {
+        let san = s.trim_end_matches(['+', '#', '!', '?']);
+
+        // Castling, also written with zeros.
+        let castle_side = match san {
+            "O-O" | "0-0" => Some(CastleSide::King),
+            "O-O-O" | "0-0-0" => Some(CastleSide::Queen),
+            _ => None,
+        };
+
+        if let Some(side) = castle_side {
+            let to = side.king_to(self.get_side_to_move());
+            let mut found = None;
+            movegen::gen_legals(self, |mv| if mv.is_castle() && mv.to() == to {found = Some(mv)});
+            return found.ok_or_else(|| Error::msg(format!("Illegal castle in san move: {:?}.", s)));
+        }
+
+        if !san.is_ascii() {
+            return Err(Error::msg(format!("Invalid san move: {:?}.", s)));
+        }
+
+        // The piece moving, pawns having no letter.
+        let (piece, rest) = match san.chars().next() {
+            Some('N') => (Piece::Knight, &san[1..]),
+            Some('B') => (Piece::Bishop, &san[1..]),
+            Some('R') => (Piece::Rook, &san[1..]),
+            Some('Q') => (Piece::Queen, &san[1..]),
+            Some('K') => (Piece::King, &san[1..]),
+            _ => (Piece::Pawn, san),
+        };
+
+        // The promotion, with or without the equal sign.
+        let (rest, promote) = match rest.chars().last() {
+            Some(c @ ('N' | 'B' | 'R' | 'Q')) if piece == Piece::Pawn => {
+                let (_, promote) = Piece::from_char(c)?;
+                (rest[..rest.len() - 1].trim_end_matches('='), Some(promote))
+            },
+            _ => (rest, None),
+        };
+
+        // The destination square, preceded by the disambiguation and capture mark.
+        if rest.len() < 2 {
+            return Err(Error::msg(format!("Invalid san move: {:?}.", s)));
+        }
+        let to = Square::from_str(&rest[rest.len() - 2..])?;
+        let (mut from_x, mut from_y) = (None, None);
+
+        for c in rest[..rest.len() - 2].chars() {
+            match c {
+                'a'..='h' if from_x.is_none() => from_x = Some((c as u8 - b'a') as i8),
+                '1'..='8' if from_y.is_none() => from_y = Some((c as u8 - b'1') as i8),
+                'x' => (),
+                _ => return Err(Error::msg(format!("Invalid san move: {:?}.", s))),
+            }
+        }
+
+        let mut found = Vec::new();
+        movegen::gen_legals(self, |mv| {
+            let from = mv.from();
+
+            if !mv.is_castle() &&
+                mv.to() == to &&
+                self.get_piece_unchecked(from) == piece &&
+                from_x.is_none_or(|x| x == from.x()) &&
+                from_y.is_none_or(|y| y == from.y()) &&
+                promote == if mv.is_promote() {Some(mv.get_promote())} else {None}
+            {
+                found.push(mv);
+            }
+        });
+
+        match found[..] {
+            [mv] => Ok(mv),
+            [] => Err(Error::msg(format!("No legal move matches the san move: {:?}.", s))),
+            _ => Err(Error::msg(format!("Ambiguous san move: {:?}.", s))),
+        }
+    }
+
+    /// Parses and does the given moves in order, in san or uci notation, returning how many were
+    /// done. On the first move that can't be parsed or is illegal, returns its index along with the
+    /// error, the board being left in the position reached by the moves before it.
+    pub fn play_moves<'a, I: IntoIterator<Item = &'a str>>(&mut self, moves: I) -> Result<usize, (usize, Error)> {
+        let mut count = 0;
+
+        for s in moves {
+            let mv = self.parse_san(s).or_else(|_| self.parse_move(s)).map_err(|e| (count, e))?;
+            self.do_move(mv);
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Like parse_move(), but lenient with front-ends that omit the promotion suffix: a four
+    /// characters long move of a pawn to the last rank (e7e8) promotes to the default piece
+    /// instead of being refused. Moves with an explicit promotion are unaffected.
+    pub fn parse_move_default_promote(&self, s: &str, default: Piece) -> Result<Move> {
+        if s.len() == 4 {
+            let from = Square::from_str(&s[0..2])?;
+            let to = Square::from_str(&s[2..4])?;
+
+            if self.get_piece(from) == Some((self.get_side_to_move(), Piece::Pawn)) && (to.y() == 0 || to.y() == 7) {
+                return self.make_move(from, to, Some(default));
+            }
+        }
+
+        self.parse_move(s)
+    }
+
+    /// Encodes the board into a compact, 32 bytes long binary format: the occupancy,
+    /// followed by a nibble (color and piece) per occupied square, then the side to move,
+    /// the castle rights, the en passant square and the move counters. The history
+    /// of the board is not encoded. Panics if there are more than 32 pieces on the board.
+    pub fn to_packed(&self) -> [u8; 32] {
+        let mut packed = [0; 32];
+        let occ = self.get_occupancy().all();
+
+        assert!(occ.count() <= 32, "Too many pieces to pack the board.");
+
+        packed[0..8].copy_from_slice(&occ.0.to_le_bytes());
+
+        for (i, sq) in occ.iter_squares().enumerate() {
+            let (color, piece) = self.get_piece(sq).unwrap();
+            let nibble = (usize::from(color) << 3 | usize::from(piece)) as u8;
+            packed[8 + i / 2] |= nibble << (4 * (i % 2));
+        }
+
+        packed[24] = usize::from(self.get_side_to_move()) as u8;
+        packed[25] = self.get_castle_rights().raw();
+        packed[26] = match self.get_ep_square() {
+            EnPassantSquare::Some(sq) => usize::from(sq) as u8,
+            EnPassantSquare::None => 0xFF,
+        };
+        packed[27] = self.get_halfmove();
+        packed[28..30].copy_from_slice(&self.get_ply().to_le_bytes());
+
+        packed
+    }
+
     /// Pretty-prints the board into a terminal, with emojis for pieces and ansi colors for squares.
     pub fn pretty_print(&self) -> String {
         const RESET: &str = "\x1b[0m";
@@ -771,6 +1871,51 @@ impl Board {
         (BitBoard::between(from, to) & self.occ.all).empty()
     }
 
+    /// Returns true if the squares the king and the rook of that color go through when castling
+    /// on that side, destinations included, are empty (but for that king and that rook).
+    #[inline]
+    pub(crate) fn is_castle_path_clear(&self, color: Color, side: CastleSide) -> bool {
+        let king = self.castle_setup.king(color);
+        let rook = self.castle_setup.rook(color, side);
+        let (king_to, rook_to) = (side.king_to(color), side.rook_to(color));
+
+        let path = BitBoard::between(king, king_to) | BitBoard::from(king_to) | BitBoard::between(rook, rook_to) | BitBoard::from(rook_to);
+        let movers = BitBoard::from(king) | BitBoard::from(rook);
+
+        (path & !movers & self.occ.all).empty()
+    }
+
+    /// Returns the castle move of that color on that side. In Chess960, it is
+    /// displayed as the king taking the rook it castles with.
+    #[inline]
+    pub(crate) fn castle_move(&self, color: Color, side: CastleSide) -> Move {
+        let king = self.castle_setup.king(color);
+
+        if self.castle_setup.is_standard() {
+            Move::castle(king, side.king_to(color))
+        } else {
+            Move::castle_960(king, side.king_to(color), self.castle_setup.rook(color, side))
+        }
+    }
+
+    /// Returns the bitboard of the attackers of both colors to that square, for the given occupancy.
+    /// Pieces missing from the occupancy are still returned. Does not take en passant into account.
+    #[inline]
+    pub(crate) fn all_attackers_to(&self, sq: Square, occ: BitBoard) -> BitBoard {
+        let queens = self.get_bitboard(Color::White, Piece::Queen) | self.get_bitboard(Color::Black, Piece::Queen);
+        let bishops = self.get_bitboard(Color::White, Piece::Bishop) | self.get_bitboard(Color::Black, Piece::Bishop);
+        let rooks = self.get_bitboard(Color::White, Piece::Rook) | self.get_bitboard(Color::Black, Piece::Rook);
+        let knights = self.get_bitboard(Color::White, Piece::Knight) | self.get_bitboard(Color::Black, Piece::Knight);
+        let kings = self.get_bitboard(Color::White, Piece::King) | self.get_bitboard(Color::Black, Piece::King);
+
+        attacks::pawn(Color::Black, sq) & self.get_bitboard(Color::White, Piece::Pawn)
+        | attacks::pawn(Color::White, sq) & self.get_bitboard(Color::Black, Piece::Pawn)
+        | attacks::rook(sq, occ) & (rooks | queens)
+        | attacks::knight(sq) & knights
+        | attacks::bishop(sq, occ) & (bishops | queens)
+        | attacks::king(sq) & kings
+    }
+
     /// Returns the bitboard of all the attackers to that square. Does not take
     /// en passant into account.
     #[inline]
@@ -792,9 +1937,9 @@ impl Board {
 
 impl Board {
     /// Places a piece of the given color on the given square. If ZOBRIST is true, 
-    /// updates the zobrist key accordingly.
+    /// updates the zobrist key accordingly. If EVAL is true, notifies the listener.
     #[inline]
-    fn place_piece<const ZOBRIST: bool>(&mut self, color: Color, piece: Piece, sq: Square) {
+    fn place_piece<const ZOBRIST: bool, const EVAL: bool>(&mut self, color: Color, piece: Piece, sq: Square, listener: &mut impl EvalListener) {
         self.mailbox[usize::from(sq)] = Some((color, piece));
         
         let mask = sq.into();
@@ -802,15 +1947,24 @@ impl Board {
         self.occ.all ^= mask;
         self.occ.colored[usize::from(color)] ^= mask;
 
+        let sign = if color == Color::White {1} else {-1};
+        self.material_mg += sign * piece.mg_value();
+        self.material_eg += sign * piece.eg_value();
+        self.phase += piece.phase_weight();
+
         if ZOBRIST {
             self.state.zobrist ^= Zobrist::from((color, piece, sq));
         }
+
+        if EVAL {
+            listener.on_place(color, piece, sq);
+        }
     }
 
     /// Removes the piece on the given square. If ZOBRIST is true, updates the
-    /// zobrist key accordingly.
+    /// zobrist key accordingly. If EVAL is true, notifies the listener.
     #[inline]
-    fn remove_piece<const ZOBRIST: bool>(&mut self, sq: Square) -> (Color, Piece) {
+    fn remove_piece<const ZOBRIST: bool, const EVAL: bool>(&mut self, sq: Square, listener: &mut impl EvalListener) -> (Color, Piece) {
         let (color, piece) = self.mailbox[usize::from(sq)].unwrap();
         self.mailbox[usize::from(sq)] = None;
         
@@ -819,20 +1973,282 @@ impl Board {
         self.occ.all ^= mask;
         self.occ.colored[usize::from(color)] ^= mask;
 
+        let sign = if color == Color::White {1} else {-1};
+        self.material_mg -= sign * piece.mg_value();
+        self.material_eg -= sign * piece.eg_value();
+        self.phase -= piece.phase_weight();
+
         if ZOBRIST {
             self.state.zobrist ^= Zobrist::from((color, piece, sq));
         }
 
-        (color, piece)
-    }
+        if EVAL {
+            listener.on_remove(color, piece, sq);
+        }
+
+        (color, piece)
+    }
+
+    /// Dispalces a piece between the two given squares. If ZOBRIST is true, updates the
+    /// zobrist key accordingly. If EVAL is true, notifies the listener of a single move.
+    #[inline]
+    fn displace_piece<const ZOBRIST: bool, const EVAL: bool>(&mut self, from: Square, to: Square, listener: &mut impl EvalListener) -> (Color, Piece) {
+        let (color, piece) = self.remove_piece::<ZOBRIST, false>(from, &mut ());
+        self.place_piece::<ZOBRIST, false>(color, piece, to, &mut ());
+
+        if EVAL {
+            listener.on_move(color, piece, from, to);
+        }
+
+        (color, piece)
+    }
+
+    /// Returns true if the side to move has at least one legal move. The moves are not collected.
+    #[inline]
+    fn has_legal_moves(&self) -> bool {
+        let mut any = false;
+        movegen::gen_legals(self, |_| any = true);
+        any
+    }
+
+    /// Returns the starting and ending squares of the rook for a castle
+    /// with the king landing on the given square.
+    #[inline]
+    fn castle_rook_squares(&self, king_to: Square) -> (Square, Square) {
+        let (color, side) = CastleSide::from_king_to(king_to).unwrap();
+        (self.castle_setup.rook(color, side), side.rook_to(color))
+    }
+
+    /// Returns the rank of the king of that color, along with the squares in front of it on
+    /// its file and on the adjacent files (only one adjacent file when the king is on the edge).
+    #[inline]
+    fn king_files_fronts(&self, color: Color) -> impl Iterator<Item = (i8, BitBoard)> {
+        let king_sq = self.king_sq(color);
+
+        (-1..=1).filter_map(move |dx| king_sq.displace((dx, 0))).map(move |sq| {
+            (king_sq.y(), pawns::front_span(color, sq))
+        })
+    }
+
+    /// Parses a board from a string in fen representation, with 6 fields or 4 if the move counters
+    /// are omitted, which then default to 0 and 1. If strict is false, a fullmove
+    /// number of 0 is read as 1 and out of range move counters are saturated, instead of
+    /// being rejected. If chess960 is true, the castle rights designate the outermost rook
+    /// on each side of the king, wherever the king and that rook stand on the back rank.
+    fn parse_fen(s: &str, strict: bool, chess960: bool) -> Result<Board> {
+        let mut fields: Vec<_> = s.split(' ').collect();
+
+        // Like in epd, the move counters may be omitted.
+        match fields.len() {
+            4 => fields.extend(["0", "1"]),
+            6 => (),
+            n => return Err(Error::msg(format!("Invalid number of fields in fen string: expected 4 or 6, got {}.", n))),
+        }
+
+        // Parse the fen string later.
+        let ranks: Vec<_> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(Error::msg("Invalid number of ranks in fen string."));
+        }
+
+        // An empty board.
+        let mut board = Board::default();
+
+        // Parse the state arguments.
+        board.state.side_to_move = Color::from_str(fields[1])?;
+        // Castle rights are parsed once the kings and rooks are placed, for Chess960.
+        let castle_rights = fields[2];
+        board.state.ep_square = EnPassantSquare::from_str(fields[3])?;
+
+        // Parse the move counters.
+        let halfmove = fields[4];
+        let halfmove = u64::from_str(halfmove).map_err(|_| Error::msg(format!("Invalid halfmove clock in fen string: {:?}.", halfmove)))?;
+        let fullmove = fields[5];
+        let fullmove = u64::from_str(fullmove).map_err(|_| Error::msg(format!("Invalid fullmove number in fen string: {:?}.", fullmove)))?;
+
+        // The largest fullmove number such that the ply fits in a u16.
+        const MAX_FULLMOVE: u64 = (u16::MAX as u64 - 1) / 2 + 1;
+
+        if strict {
+            if halfmove > u64::from(u8::MAX) {
+                return Err(Error::msg("Halfmove clock too large in fen string."));
+            }
+            if !(1..=MAX_FULLMOVE).contains(&fullmove) {
+                return Err(Error::msg("Fullmove number out of range in fen string."));
+            }
+        }
+
+        board.state.halfmove = halfmove.min(u64::from(u8::MAX)) as u8;
+        board.ply = (2 * (fullmove.clamp(1, MAX_FULLMOVE) - 1)) as u16 + usize::from(board.state.side_to_move) as u16;
+
+        // Parse the fen board.
+        for (y, &rank) in ranks.iter().enumerate() {           
+            let mut x = 0;
+            for c in rank.chars() {
+                match c {
+                    '1'..='8' => x += c.to_digit(10).unwrap(),
+                    _ => {
+                        let (color, piece) = Piece::from_char(c)?;
+                        let sq = Square::from((x as i8, 7 - y as i8));
+                        board.place_piece::<false, false>(color, piece, sq, &mut ());
+                        x += 1;
+                    }
+                }
+                
+                if x > 8 {
+                    return Err(Error::msg("Rank too large in fen string."));
+                }
+            }
+
+            if x != 8 {
+                return Err(Error::msg("Rank too small in fen string."));
+            }
+        }
+
+        // Check that both sides have only one king
+        for color in Color::COLORS {
+            if !board.get_bitboard(color, Piece::King).is_one() {
+                return Err(Error::msg("Invalid number of kings on the board."));
+            }
+        }
+
+        // In Chess960, find the rooks the kings castle with: by default, the outermost ones.
+        if chess960 {
+            for color in Color::COLORS {
+                let king_sq = board.king_sq(color);
+                let back_rank = if color == Color::White {0} else {7};
+                if king_sq.y() != back_rank {
+                    continue;
+                }
+                board.castle_setup.set_king(color, king_sq);
+
+                for side in CastleSide::SIDES {
+                    let files: Vec<i8> = match side {
+                        CastleSide::King => (king_sq.x() + 1..8).rev().collect(),
+                        CastleSide::Queen => (0..king_sq.x()).collect(),
+                    };
+
+                    let rook_sq = files.into_iter()
+                        .map(|x| Square::from((x, back_rank)))
+                        .find(|&sq| board.get_piece(sq) == Some((color, Piece::Rook)));
+
+                    if let Some(rook_sq) = rook_sq {
+                        board.castle_setup.set_rook(color, side, rook_sq);
+                    }
+                }
+            }
+
+            board.state.castle_rights = CastleRights::parse_with(castle_rights, &mut board.castle_setup)?;
+        } else {
+            board.state.castle_rights = CastleRights::from_str(castle_rights)?;
+        }
+
+        // Clear the castle rights that are impossible, so that castling never misses it's rook.
+        let castle_rights = board.valid_castle_rights(board.state.castle_rights);
+        if strict && castle_rights != board.state.castle_rights {
+            return Err(Error::msg("Impossible castle rights in fen string."));
+        }
+        board.state.castle_rights = castle_rights;
+
+        // The fen holds the en passant target square, we store the square of the pawn behind it.
+        if let EnPassantSquare::Some(target) = board.state.ep_square {
+            let us = board.get_side_to_move();
+            let them = board.get_other_side();
+
+            let pawn_sq = attacks::pawn_push(them, target)
+                .filter(|&sq| sq.y() == if us == Color::White {4} else {3})
+                .filter(|&sq| board.get_piece(sq) == Some((them, Piece::Pawn)));
+
+            board.state.ep_square = match pawn_sq {
+                Some(sq) => EnPassantSquare::Some(sq),
+                None if strict => return Err(Error::msg("Invalid en passant square in fen string.")),
+                None => EnPassantSquare::None,
+            };
+        }
+
+        // Compute the checkers, pinned pieces and zobrist key of the board.
+        board.refresh_state();
+
+        // TODO: further checks ?
+ 
+        Ok(board)
+    }
+
+    /// Determines why that move, which was refused by is_pseudo_legal() or is_legal(), is illegal.
+    fn illegal_reason(&self, mv: Move) -> IllegalMoveReason {
+        let (from, to) = mv.squares();
+        let us = self.get_side_to_move();
+
+        let piece = match self.get_piece(from) {
+            None => return IllegalMoveReason::NoPiece,
+            Some((color, _)) if color != us => return IllegalMoveReason::NotYourPiece,
+            Some((_, piece)) => piece,
+        };
+
+        if mv.is_castle() {
+            let side = match CastleSide::from_king_to(to) {
+                Some((color, side)) if color == us && piece == Piece::King && from == self.castle_setup.king(us) => side,
+                _ => return IllegalMoveReason::Unreachable,
+            };
+
+            return if !self.get_castle_rights().can(us, side) {
+                IllegalMoveReason::NoCastleRights
+            } else if self.get_piece(self.castle_setup.rook(us, side)) != Some((us, Piece::Rook)) || !self.is_castle_path_clear(us, side) {
+                IllegalMoveReason::CastleBlocked
+            } else {
+                IllegalMoveReason::CastleThroughCheck
+            };
+        }
+
+        if matches!(self.get_piece(to), Some((color, _)) if color == us) {
+            return IllegalMoveReason::OwnPieceOnDestination;
+        }
+
+        if piece == Piece::Pawn && BitBoard::promote_rank(us).contains(from) && !mv.is_promote() {
+            return IllegalMoveReason::MissingPromotion;
+        }
+
+        if mv.is_promote() && (piece != Piece::Pawn || !BitBoard::promote_rank(us).contains(from)) {
+            return IllegalMoveReason::InvalidPromotion;
+        }
+
+        if !self.is_pseudo_legal(mv) {
+            let checkers = self.get_checkers();
+
+            if piece != Piece::King && checkers.not_empty() {
+                // SAFE: there is at least one checker
+                let checker = unsafe {checkers.as_square_unchecked()};
+                let mask = BitBoard::between(self.king_sq(us), checker) | checkers;
+
+                if checkers.more_than_one() || !mask.contains(to) {
+                    return IllegalMoveReason::DoesNotParryCheck;
+                }
+            }
+
+            return IllegalMoveReason::Unreachable;
+        }
+
+        // En passant captures are not filtered by is_pseudo_legal() when in check: the pawn
+        // taken may be the checker, otherwise they must block the check.
+        if mv.is_en_passant() {
+            let checkers = self.get_checkers() & !BitBoard::from(self.get_ep_square().unwrap());
 
-    /// Dispalces a piece between the two given squares. If ZOBRIST is true, updates the
-    /// zobrist key accordingly.
-    #[inline]
-    fn displace_piece<const ZOBRIST: bool>(&mut self, from: Square, to: Square) -> (Color, Piece) {
-        let (color, piece) = self.remove_piece::<ZOBRIST>(from);
-        self.place_piece::<ZOBRIST>(color, piece, to);
-        (color, piece)
+            if checkers.not_empty() {
+                // SAFE: there is at least one checker
+                let checker = unsafe {checkers.as_square_unchecked()};
+                let mask = BitBoard::between(self.king_sq(us), checker);
+
+                if checkers.more_than_one() || !mask.contains(to) {
+                    return IllegalMoveReason::DoesNotParryCheck;
+                }
+            }
+        }
+
+        if piece == Piece::King {
+            IllegalMoveReason::IntoCheck
+        } else {
+            IllegalMoveReason::ExposesKing
+        }
     }
 
     /// The bitboard of the checkers to the current king.
@@ -842,6 +2258,39 @@ impl Board {
         self.attackers_to(self.king_sq(self.get_side_to_move()), occ)
     }
 
+    /// The middlegame and endgame material balances and the game phase of the board,
+    /// computed from scratch.
+    fn material(&self) -> (i32, i32, u8) {
+        let (mut mg, mut eg, mut phase) = (0, 0, 0);
+
+        for sq in self.get_occupancy().all().iter_squares() {
+            let (color, piece) = self.get_piece(sq).unwrap();
+            let sign = if color == Color::White {1} else {-1};
+            mg += sign * piece.mg_value();
+            eg += sign * piece.eg_value();
+            phase += piece.phase_weight();
+        }
+
+        (mg, eg, phase)
+    }
+
+    /// The zobrist key of the board, computed from scratch.
+    #[inline]
+    fn zobrist(&self) -> Zobrist {
+        let mut zobrist = Zobrist::from(self.get_castle_rights()) ^ Zobrist::from(self.get_ep_square());
+
+        for sq in self.get_occupancy().all().iter_squares() {
+            let (color, piece) = self.get_piece(sq).unwrap();
+            zobrist ^= Zobrist::from((color, piece, sq));
+        }
+
+        // The zobrist key is inverted when it is black's turn to move.
+        match self.get_side_to_move() {
+            Color::White => zobrist,
+            Color::Black => !zobrist,
+        }
+    }
+
     /// The bitboard of the currently pinned pieces.
     #[inline]
     fn pinned(&self) -> BitBoard {
@@ -882,6 +2331,12 @@ impl Default for Board {
             bitboards: Default::default(),
             mailbox: [None; 64],
             occ: Occupancy::default(),
+
+            material_mg: 0,
+            material_eg: 0,
+            phase: 0,
+
+            castle_setup: CastleSetup::STANDARD,
         
             state: StateInfo::default(),
             prev_states: Vec::new(),
@@ -920,10 +2375,16 @@ impl fmt::Display for Board {
             }
         }
 
+        // The fen holds the en passant target square, not the one of the pawn.
+        let ep_target = match self.get_ep_square() {
+            EnPassantSquare::Some(sq) => EnPassantSquare::Some(attacks::pawn_push(self.get_side_to_move(), sq).unwrap()),
+            EnPassantSquare::None => EnPassantSquare::None,
+        };
+
         write!(f, " {} {} {} {} {}", 
             self.get_side_to_move(),
-            self.get_castle_rights(),
-            self.get_ep_square(),
+            self.get_castle_rights().to_string_with(&self.castle_setup),
+            ep_target,
             self.get_halfmove(),
             1 + self.get_ply() / 2,
         )?;
@@ -935,72 +2396,938 @@ impl fmt::Display for Board {
 impl<'a> FromStr for Board {
     type Err = Error;
 
-    /// Tries to parse a board from a string in fen representation.
+    /// Tries to parse a board from a string in fen representation. The move counters
+    /// are parsed leniently, see Board::parse_fen().
     fn from_str(s: &str) -> Result<Board> {
-        let mut split = s.split(' ');
+        Board::parse_fen(s, false, false)
+    }
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
 
-        // Closure to get the next arg, or return an error if there is not.
-        let mut next_arg = || split.next().ok_or_else(|| Error::msg("not enough arguments in fen string"));
+#[cfg(test)]
+mod tests {
+    use crate::bitboard::BitBoard;
+    use crate::board::{Board, EvalListener, IllegalMoveReason, Status};
+    use crate::color::Color;
+    use crate::en_passant::EnPassantSquare;
+    use crate::movegen;
+    use crate::piece::Piece;
+    use crate::square::Square;
+
+    /// Checks that the incremental zobrist key matches the one computed from scratch,
+    /// after doing and undoing every move up to the given depth.
+    fn check_zobrist(board: &mut Board, depth: usize) {
+        assert_eq!(board.get_zobrist(), board.zobrist(), "mismatch on board: {}", board);
+
+        if depth == 0 {
+            return;
+        }
 
-        // Parse the fen string later.
-        let ranks: Vec<_> = next_arg()?.split('/').collect();
-        if ranks.len() != 8 {
-            return Err(Error::msg("Invalid number of ranks in fen string."));
+        let mut buffer = Vec::new();
+        movegen::legals(board, &mut buffer);
+
+        for mv in buffer {
+            board.do_move(mv);
+            check_zobrist(board, depth - 1);
+            board.undo_move(mv);
+            assert_eq!(board.get_zobrist(), board.zobrist(), "mismatch after undoing {}", mv);
         }
+    }
 
-        // An empty board.
-        let mut board = Board::default();
+    /// A listener keeping its own copy of the mailbox up to date.
+    struct Mailbox([Option<(Color, Piece)>; 64]);
 
-        // Parse the state arguments.
-        board.state.side_to_move = Color::from_str(next_arg()?)?;
-        board.state.castle_rights = CastleRights::from_str(next_arg()?)?;
-        board.state.ep_square = EnPassantSquare::from_str(next_arg()?)?;
-        board.state.halfmove = u8::from_str(next_arg()?)?;
-        board.ply = u16::from_str(next_arg()?)?;
+    impl EvalListener for Mailbox {
+        fn on_place(&mut self, color: Color, piece: Piece, sq: Square) {
+            assert_eq!(self.0[usize::from(sq)], None);
+            self.0[usize::from(sq)] = Some((color, piece));
+        }
 
-        if split.next().is_some() {
-            return Err(Error::msg("Too many arguments in fen string."));
+        fn on_remove(&mut self, color: Color, piece: Piece, sq: Square) {
+            assert_eq!(self.0[usize::from(sq)], Some((color, piece)));
+            self.0[usize::from(sq)] = None;
         }
 
-        // Parse the fen board.
-        for (y, &rank) in ranks.iter().enumerate() {           
-            let mut x = 0;
-            for c in rank.chars() {
-                match c {
-                    '1'..='8' => x += c.to_digit(10).unwrap(),
-                    _ => {
-                        let (color, piece) = Piece::from_char(c)?;
-                        let sq = Square::from((x as i8, 7 - y as i8));
-                        board.get_bitboard(Color::White, Piece::Pawn);
-                        board.place_piece::<true>(color, piece, sq);
-                        x += 1;
+        fn on_move(&mut self, color: Color, piece: Piece, from: Square, to: Square) {
+            self.on_remove(color, piece, from);
+            self.on_place(color, piece, to);
+        }
+    }
+
+    /// Checks that the listener's mailbox matches the board, after doing and undoing
+    /// every move up to the given depth.
+    fn check_listener(board: &mut Board, mailbox: &mut Mailbox, depth: usize) {
+        assert!((0..64).map(Square::from).all(|sq| board.get_piece(sq) == mailbox.0[usize::from(sq)]), "mismatch on board: {}", board);
+
+        if depth == 0 {
+            return;
+        }
+
+        let mut buffer = Vec::new();
+        movegen::legals(board, &mut buffer);
+
+        for mv in buffer {
+            board.do_move_with(mv, mailbox);
+            check_listener(board, mailbox, depth - 1);
+            board.undo_move_with(mv, mailbox);
+        }
+    }
+
+    #[test]
+    fn make_move() {
+        crate::init();
+
+        let board = Board::new("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(board.make_move(Square::B7, Square::B8, None).is_err());
+        assert!(board.make_move(Square::B7, Square::B8, Some(Piece::King)).is_err());
+        assert!(board.make_move(Square::B7, Square::B8, Some(Piece::Queen)).unwrap().is_promote());
+        assert!(board.make_move(Square::E1, Square::G1, None).unwrap().is_castle());
+        assert!(board.make_move(Square::H1, Square::D1, None).is_err());
+        assert!(board.make_move(Square::H1, Square::H7, None).unwrap().is_quiet());
+
+        assert!(board.parse_move("b7b8").is_err());
+        assert_eq!(board.parse_move_default_promote("b7b8", Piece::Queen).unwrap(), board.parse_move("b7b8q").unwrap());
+        assert_eq!(board.parse_move_default_promote("b7b8n", Piece::Queen).unwrap(), board.parse_move("b7b8n").unwrap());
+        assert_eq!(board.parse_move_default_promote("h1h7", Piece::Queen).unwrap(), board.parse_move("h1h7").unwrap());
+    }
+
+    #[test]
+    fn play_moves() {
+        crate::init();
+
+        let mut board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(board.play_moves("e2e4 e7e5 g1f3".split(' ')).unwrap(), 3);
+
+        let (index, _) = board.play_moves(vec!["b8c6", "f1c4", "e8e7x", "g8f6"]).unwrap_err();
+        assert_eq!(index, 2);
+        assert_eq!(board.to_string(), "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3");
+
+        assert_eq!(board.play_moves(vec!["e1g1"]).unwrap_err().0, 0);
+        assert_eq!(board.play_moves(None).unwrap(), 0);
+
+        // San and uci may be mixed.
+        assert_eq!(board.play_moves(vec!["Nf6", "O-O", "f8c5", "d3"]).unwrap(), 4);
+        assert_eq!(board.to_string(), "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/3P1N2/PPP2PPP/RNBQ1RK1 b kq - 0 5");
+    }
+
+    #[test]
+    fn parse_san() {
+        crate::init();
+
+        let board = Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        for (san, uci) in [
+            ("O-O", "e1g1"), ("O-O-O+", "e1c1"), ("dxe6", "d5e6"), ("Nxf7!?", "e5f7"),
+            ("Qxf6", "f3f6"), ("a4", "a2a4"), ("Bxa6", "e2a6"), ("Kd1", "e1d1"),
+        ] {
+            assert_eq!(board.parse_san(san).unwrap(), board.parse_move(uci).unwrap(), "{}", san);
+        }
+
+        // Impossible or malformed.
+        assert!(board.parse_san("Rb2").is_err());
+        assert!(board.parse_san("Nb6").is_err());
+        assert!(board.parse_san("e9").is_err());
+        assert!(board.parse_san("").is_err());
+
+        let board = Board::new("4k3/8/8/R7/8/8/8/RN2KN2 w - - 0 1").unwrap();
+        assert!(board.parse_san("Nd2").is_err());
+        assert_eq!(board.parse_san("Nbd2").unwrap(), board.parse_move("b1d2").unwrap());
+        assert_eq!(board.parse_san("R1a3").unwrap(), board.parse_move("a1a3").unwrap());
+        assert_eq!(board.parse_san("Ra5a3").unwrap(), board.parse_move("a5a3").unwrap());
+
+        let board = Board::new("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(board.parse_san("a8").is_err());
+        assert_eq!(board.parse_san("a8=Q+").unwrap(), board.parse_move("a7a8q").unwrap());
+        assert_eq!(board.parse_san("axb8N").unwrap(), board.parse_move("a7b8n").unwrap());
+
+        // Round trip with move_to_san().
+        let mut buffer = Vec::new();
+        movegen::legals(&board, &mut buffer);
+        for mv in buffer {
+            assert_eq!(board.parse_san(&board.move_to_san(mv)).unwrap(), mv);
+        }
+    }
+
+    #[test]
+    fn capture_or_quiet() {
+        crate::init();
+
+        let board = Board::new("r3k3/1P6/8/3pP3/8/8/8/4K2R w K d6 0 1").unwrap();
+        assert!(board.is_capture_move(Square::E5, Square::D6));
+        assert!(board.is_capture_move(Square::B7, Square::A8));
+        assert!(!board.is_capture_move(Square::H1, Square::H8));
+        assert!(!board.is_capture_move(Square::A8, Square::A1));
+        assert!(!board.is_capture_move(Square::E1, Square::H1));
+
+        assert!(board.is_quiet_move(Square::E5, Square::E6));
+        assert!(board.is_quiet_move(Square::E1, Square::G1));
+        assert!(!board.is_quiet_move(Square::B7, Square::B8));
+        assert!(!board.is_quiet_move(Square::E5, Square::D6));
+        assert!(!board.is_quiet_move(Square::D5, Square::D4));
+    }
+
+    #[test]
+    fn illegal_reason() {
+        crate::init();
+
+        let reason = |fen: &str, mv: &str| {
+            Board::new(fen).unwrap().parse_move(mv).unwrap_err().downcast::<IllegalMoveReason>().unwrap()
+        };
+
+        let fen = "r3k2r/8/8/8/1b6/8/3P4/R3K2R w Qk - 0 1";
+        assert_eq!(reason(fen, "e3e4"), IllegalMoveReason::NoPiece);
+        assert_eq!(reason(fen, "b4c3"), IllegalMoveReason::NotYourPiece);
+        assert_eq!(reason(fen, "a1e1"), IllegalMoveReason::OwnPieceOnDestination);
+        assert_eq!(reason(fen, "h1g2"), IllegalMoveReason::Unreachable);
+        assert_eq!(reason(fen, "e1g1"), IllegalMoveReason::NoCastleRights);
+        assert_eq!(reason(fen, "d2d3"), IllegalMoveReason::ExposesKing);
+
+        let fen = "4k3/1P6/8/8/8/8/8/R3K2r w Q - 0 1";
+        assert_eq!(reason(fen, "b7b8"), IllegalMoveReason::MissingPromotion);
+        assert_eq!(reason(fen, "a1a2"), IllegalMoveReason::DoesNotParryCheck);
+        assert_eq!(reason(fen, "e1c1"), IllegalMoveReason::CastleThroughCheck);
+        assert_eq!(reason(fen, "e1d1"), IllegalMoveReason::IntoCheck);
+    }
+
+    #[test]
+    fn threatened_pieces() {
+        crate::init();
+
+        // The knight is attacked by the pawn, which is attacked by the rook although defended.
+        let board = Board::new("4k3/8/4p3/3p4/4N3/8/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(board.threatened_pieces(Color::White), BitBoard::from(Square::E4));
+        assert_eq!(board.threatened_pieces(Color::Black), BitBoard::from(Square::D5));
+
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert!(board.threatened_pieces(Color::White).empty());
+        assert!(board.threatened_pieces(Color::Black).empty());
+    }
+
+    #[test]
+    fn control_map() {
+        crate::init();
+
+        let board = Board::new("4k3/8/8/3p4/8/8/3R1N2/3RK3 w - - 0 1").unwrap();
+        let map = board.control_map();
+
+        // The rook on d1 x-rays through the one on d2, which doesn't count. The pawn blocks d6.
+        assert_eq!(map[usize::from(Square::D4)], 1);
+        assert_eq!(map[usize::from(Square::D5)], 1);
+        assert_eq!(map[usize::from(Square::D6)], 0);
+        assert_eq!(map[usize::from(Square::D2)], 2);
+
+        // The knight and the pawn contest e4, the pawn alone controls c4.
+        assert_eq!(board.square_control(Square::E4), (1, 1));
+        assert_eq!(map[usize::from(Square::E4)], 0);
+        assert_eq!(map[usize::from(Square::C4)], -1);
+        assert_eq!(map[usize::from(Square::D8)], -1);
+        assert_eq!(map[usize::from(Square::A8)], 0);
+    }
+
+    #[test]
+    fn passed_pawns() {
+        crate::init();
+
+        // The a and b pawns of white are passed, and so is the h pawn of black.
+        let board = Board::new("8/5k1p/1P6/P4p2/5P2/6K1/8/8 w - - 0 1").unwrap();
+        assert_eq!(board.passed_pawns(Color::White), BitBoard::from(Square::A5) | BitBoard::from(Square::B6));
+        assert_eq!(board.passed_pawns(Color::Black), BitBoard::from(Square::H7));
+    }
+
+    #[test]
+    fn pawn_structure() {
+        crate::init();
+
+        // White has tripled c pawns and an isolated h pawn, black doubled and isolated a pawns.
+        let board = Board::new("4k3/p4ppp/p7/2P5/8/2P4P/1PP5/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.doubled_pawns(Color::White), BitBoard::from(Square::C2) | BitBoard::from(Square::C3));
+        assert_eq!(board.doubled_pawns(Color::Black), BitBoard::from(Square::A7));
+        assert_eq!(board.isolated_pawns(Color::White), BitBoard::from(Square::H3));
+        assert_eq!(board.isolated_pawns(Color::Black), BitBoard::from(Square::A6) | BitBoard::from(Square::A7));
+    }
+
+    #[test]
+    fn en_passant() {
+        crate::init();
+
+        // Returns the legal moves of the position, as strings.
+        let legals = |fen| {
+            let board = Board::new(fen).unwrap();
+            let mut buffer = Vec::new();
+            movegen::legals(&board, &mut buffer);
+            buffer.iter().map(|mv| mv.to_string()).collect::<Vec<_>>()
+        };
+
+        // The two pawns are pinned together along the rank.
+        assert!(!legals("8/8/8/K1Pp3r/8/8/8/k7 w - d6 0 1").contains(&"c5d6".to_string()));
+        assert!(!legals("8/8/8/8/k2Pp2Q/8/8/3K4 b - d3 0 1").contains(&"e4d3".to_string()));
+        // Another piece stands between them and the rook.
+        assert!(legals("8/8/8/K1Pp1n1r/8/8/8/k7 w - d6 0 1").contains(&"c5d6".to_string()));
+
+        // The capturing pawn is pinned along a diagonal.
+        assert!(!legals("8/K7/8/2Pp4/8/8/8/k5b1 w - d6 0 1").contains(&"c5d6".to_string()));
+        // But it may capture along the pin.
+        assert!(legals("5b2/8/8/2Pp4/8/K7/8/k7 w - d6 0 1").contains(&"c5d6".to_string()));
+
+        // Capturing the pawn giving check.
+        assert!(legals("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1").contains(&"e4d3".to_string()));
+        // Capturing does not parry a check from another piece.
+        assert!(!legals("8/8/2k5/8/3Pp3/8/8/2R1K3 b - d3 0 1").contains(&"e4d3".to_string()));
+        // Capturing blocks a check.
+        assert!(legals("8/8/8/8/3Pp3/k6R/8/4K3 b - d3 0 1").contains(&"e4d3".to_string()));
+
+        // The en passant square is written back as the target square.
+        for fen in &["8/8/8/K1Pp3r/8/8/8/k7 w - d6 0 1", "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1"] {
+            assert_eq!(Board::new(fen).unwrap().to_string(), *fen);
+        }
+
+        // An en passant square with no pawn behind it is dropped, or refused when strict.
+        assert_eq!(Board::new("4k3/8/8/8/8/8/8/4K3 w - d6 0 1").unwrap().to_string(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert!(Board::from_fen_strict("4k3/8/8/8/8/8/8/4K3 w - d6 0 1").is_err());
+
+        let reason = |fen: &str, mv: &str| {
+            Board::new(fen).unwrap().parse_move(mv).unwrap_err().downcast::<IllegalMoveReason>().unwrap()
+        };
+
+        assert_eq!(reason("8/8/2k5/8/3Pp3/8/8/2R1K3 b - d3 0 1", "e4d3"), IllegalMoveReason::DoesNotParryCheck);
+        assert_eq!(reason("8/8/8/K1Pp3r/8/8/8/k7 w - d6 0 1", "c5d6"), IllegalMoveReason::ExposesKing);
+    }
+
+    #[test]
+    fn capturable_pieces() {
+        crate::init();
+
+        // The queen may be captured twice, the pawn on g4 not at all as the bishop is pinned.
+        let board = Board::new("4k3/8/2p1r3/3q4/1N4p1/8/4B3/3RK3 w - - 0 1").unwrap();
+        assert_eq!(board.capturable_pieces(), [(Square::D5, Piece::Queen), (Square::C6, Piece::Pawn)]);
+
+        let board = Board::new("4k3/8/8/2Pp4/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(board.capturable_pieces(), [(Square::D5, Piece::Pawn)]);
+    }
+
+    #[test]
+    fn single_legal_move() {
+        crate::init();
+
+        let board = Board::new("7k/8/8/8/8/8/5PPP/r5K1 w - - 0 1").unwrap();
+        assert_eq!(board.has_single_legal_move(), None);
+
+        let board = Board::new("7k/8/8/8/8/8/6PP/r5K1 w - - 0 1").unwrap();
+        assert_eq!(board.has_single_legal_move().unwrap().to_string(), "g1f2");
+
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(board.has_single_legal_move(), None);
+
+        // No legal move at all, when checkmated or stalemated.
+        let board = Board::new("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+        assert_eq!(board.has_single_legal_move(), None);
+        let board = Board::new("7k/8/6QK/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.has_single_legal_move(), None);
+
+        // A single evasion: capturing the checker.
+        let board = Board::new("7k/R7/8/8/8/8/5PPP/r5K1 w - - 0 1").unwrap();
+        assert_eq!(board.has_single_legal_move().unwrap().to_string(), "a7a1");
+
+        // Two legal moves, the knight being pinned.
+        let board = Board::new("7k/8/8/8/8/8/8/r5NK w - - 0 1").unwrap();
+        assert_eq!(board.has_single_legal_move(), None);
+        assert_eq!(movegen::count_legals(&board), 2);
+    }
+
+    #[test]
+    fn castle_rights() {
+        crate::init();
+
+        // The rook of h1 is missing, and the black king is not on it's square.
+        let fen = "r2k3r/8/8/8/8/8/8/R3K3 w KQkq - 0 1";
+        assert_eq!(Board::new(fen).unwrap().to_string(), "r2k3r/8/8/8/8/8/8/R3K3 w Q - 0 1");
+        assert!(Board::from_fen_strict(fen).is_err());
+
+        let mut board = Board::new("r3k2r/8/8/8/8/8/8/R3K3 w Qkq - 0 1").unwrap();
+        assert!(!board.set_castle_rights("KQkq".parse().unwrap()));
+        assert_eq!(board.get_castle_rights().to_string(), "Qkq");
+
+        assert!(board.set_castle_rights("Qk".parse().unwrap()));
+        assert_eq!(board.get_castle_rights().to_string(), "Qk");
+        assert_eq!(board.get_zobrist(), board.zobrist());
+    }
+
+    #[test]
+    fn fifty_move_countdown() {
+        crate::init();
+
+        let mut board = Board::new("4k3/8/8/8/8/8/8/4K2R w - - 97 80").unwrap();
+        assert!(board.status().is_playing());
+        assert_eq!(board.plies_since_irreversible(), 97);
+        assert_eq!(board.plies_until_fifty_move_draw(), 3);
+
+        for mv in &["h1h2", "e8d8", "h2h1", "d8e8"] {
+            board.do_move(board.parse_move(mv).unwrap());
+        }
+        assert_eq!(board.plies_until_fifty_move_draw(), 0);
+        assert!(board.is_fifty_move_draw());
+        assert!(!board.is_seventy_five_move_draw());
+        assert!(!board.status().is_playing());
+
+        // A pawn move resets the clock, which saturates instead of overflowing.
+        let mut board = Board::new("4k3/8/8/8/8/8/P7/4K2R w - - 254 200").unwrap();
+        assert!(board.is_seventy_five_move_draw());
+        for mv in &["h1h2", "e8d8"] {
+            board.do_move(board.parse_move(mv).unwrap());
+        }
+        assert_eq!(board.halfmove_clock(), 255);
+        board.do_move(board.parse_move("a2a3").unwrap());
+        assert_eq!(board.halfmove_clock(), 0);
+
+        // A checkmate on the hundredth halfmove wins.
+        let mut board = Board::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80").unwrap();
+        board.do_move(board.parse_move("a1a8").unwrap());
+        assert!(board.is_fifty_move_draw());
+        assert!(matches!(board.status(), Status::Win(Color::White)));
+    }
+
+    #[test]
+    fn repetitions() {
+        crate::init();
+
+        let mut board = Board::new("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.repetition_count(), 1);
+
+        let shuffle = ["e1d1", "e8d8", "d1e1", "d8e8"];
+        for (i, mv) in shuffle.iter().cycle().take(8).enumerate() {
+            board.do_move(board.parse_move(mv).unwrap());
+            assert_eq!(board.repetition_count(), 1 + (i + 1) / 4);
+        }
+        assert!(board.is_threefold_repetition());
+        assert!(!board.status().is_playing());
+
+        // A pawn move is irreversible, earlier positions can't come back.
+        for mv in &["e2e3", "e8d8", "e1d1", "d8e8", "d1e1"] {
+            board.do_move(board.parse_move(mv).unwrap());
+        }
+        assert_eq!(board.repetition_count(), 2);
+        assert!(board.status().is_playing());
+    }
+
+    #[test]
+    fn truncate_history() {
+        crate::init();
+
+        let mut board = Board::new("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        for mv in ["e1d1", "e8d8", "d1e1", "d8e8", "e2e3", "e8d8", "e1d1", "d8e8", "d1e1"] {
+            board.do_move(board.parse_move(mv).unwrap());
+        }
+        assert_eq!(board.position_history().count(), 10);
+
+        // Only the positions since the pawn move are kept, the first of them being the current one.
+        board.truncate_history_to_last_irreversible();
+        let history: Vec<_> = board.position_history().collect();
+        assert_eq!(history.len(), 5);
+        assert_eq!(history[0].zobrist, board.get_zobrist());
+        assert_eq!(board.repetition_count(), 2);
+
+        // Repetitions are still detected.
+        for mv in ["e8d8", "e1d1", "d8e8", "d1e1"] {
+            board.do_move(board.parse_move(mv).unwrap());
+        }
+        assert_eq!(board.repetition_count(), 3);
+        assert!(board.is_threefold_repetition());
+
+        // Truncating again keeps every position since the pawn move.
+        board.truncate_history_to_last_irreversible();
+        assert_eq!(board.position_history().count(), 9);
+    }
+
+    #[test]
+    fn material() {
+        crate::init();
+
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!((board.get_material_mg(), board.get_material_eg(), board.get_phase()), (0, 0, Piece::MAX_PHASE));
+
+        // A promotion with capture, then undone.
+        let mut board = Board::new("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.get_material_mg(), Piece::Pawn.mg_value() - Piece::Rook.mg_value());
+        assert_eq!(board.get_phase(), 2);
+
+        let mv = board.parse_move("a7b8q").unwrap();
+        board.do_move(mv);
+        assert_eq!(board.get_material_eg(), Piece::Queen.eg_value());
+        assert_eq!(board.get_phase(), 4);
+
+        board.undo_move(mv);
+        assert_eq!(board.get_material_mg(), Piece::Pawn.mg_value() - Piece::Rook.mg_value());
+        assert_eq!(board.get_phase(), 2);
+    }
+
+    #[test]
+    fn with_side_to_move() {
+        crate::init();
+
+        let board = Board::new("4k3/8/8/3pP3/8/8/8/R3K3 w Q d6 0 7").unwrap();
+
+        let black = board.with_side_to_move(Color::Black);
+        assert_eq!(black.to_string(), "4k3/8/8/3pP3/8/8/8/R3K3 b Q - 0 7");
+        assert_eq!(black.get_zobrist(), Board::new(&black.to_string()).unwrap().get_zobrist());
+        assert!(black.get_checkers().empty());
+
+        let white = black.with_side_to_move(Color::White);
+        assert_eq!(white.to_string(), "4k3/8/8/3pP3/8/8/8/R3K3 w Q - 0 7");
+        assert_eq!(board.with_side_to_move(Color::White).to_string(), board.to_string());
+    }
+
+    #[test]
+    fn gives_check() {
+        crate::init();
+
+        movegen::for_each_position(&movegen::PERFT_FENS, |board, legals| {
+            let mut checking = board.checking_moves();
+
+            let mut expected = Vec::new();
+            for &mv in legals {
+                let gives_check = board.gives_check(mv);
+                board.do_move(mv);
+                assert_eq!(gives_check, board.get_checkers().not_empty(), "{} {}", board, mv);
+                if gives_check {
+                    expected.push(mv);
+                }
+                board.undo_move(mv);
+            }
+
+            checking.sort_by_key(|mv| mv.to_string());
+            expected.sort_by_key(|mv| mv.to_string());
+            assert_eq!(checking, expected, "{}", board);
+        });
+    }
+
+    #[test]
+    fn checkmate_or_stalemate() {
+        crate::init();
+
+        let board = Board::new("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+        assert!(board.is_check());
+        assert!(board.is_checkmate());
+        assert!(!board.is_stalemate());
+        assert!(matches!(board.status(), Status::Win(Color::White)));
+
+        let board = Board::new("7k/8/6QK/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(!board.is_check());
+        assert!(!board.is_checkmate());
+        assert!(board.is_stalemate());
+        assert!(matches!(board.status(), Status::Draw));
+
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert!(!board.is_checkmate());
+        assert!(!board.is_stalemate());
+        assert!(board.status().is_playing());
+    }
+
+    #[test]
+    fn see() {
+        crate::init();
+
+        let see = |fen: &str, mv: &str| {
+            let board = Board::new(fen).unwrap();
+            board.see(board.parse_move(mv).unwrap())
+        };
+
+        // Undefended pawn, and a pawn defended by a pawn.
+        assert_eq!(see("4k3/8/8/3p4/8/8/8/3RK3 w - - 0 1", "d1d5"), 82);
+        assert_eq!(see("4k3/8/2p5/3p4/8/8/8/3RK3 w - - 0 1", "d1d5"), 82 - 477);
+
+        // The rook behind the queen joins in: RxP, PxR, QxP.
+        assert_eq!(see("4k3/8/2p5/3p4/8/8/3R4/3QK3 w - - 0 1", "d2d5"), 82 - 477 + 82);
+
+        // A knight defended by a pawn, taken by a pawn: PxN, PxP.
+        assert_eq!(see("4k3/8/2p5/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5"), 337 - 82);
+
+        // A king can't capture a defended piece.
+        assert_eq!(see("3rk3/8/8/3p4/4K3/8/8/3Q4 w - - 0 1", "d1d5"), 82 - 1025 + 477);
+        assert_eq!(see("3rk3/3r4/8/3p4/4K3/8/8/3Q4 w - - 0 1", "d1d5"), 82 - 1025);
+
+        // A quiet move to an attacked square.
+        assert_eq!(see("4k3/8/2p5/8/8/8/8/3RK3 w - - 0 1", "d1d5"), -477);
+    }
+
+    #[test]
+    fn gives_checkmate() {
+        crate::init();
+
+        let mut board = Board::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert!(board.gives_checkmate(board.parse_move("a1a8").unwrap()));
+        assert!(!board.gives_checkmate(board.parse_move("a1a7").unwrap()));
+        assert_eq!(board.to_string(), "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+
+        // Stalemate is not checkmate.
+        let mut board = Board::new("7k/8/5K2/8/8/8/8/6Q1 w - - 0 1").unwrap();
+        assert!(!board.gives_checkmate(board.parse_move("g1g6").unwrap()));
+        assert!(board.gives_checkmate(board.parse_move("g1g7").unwrap()));
+    }
+
+    #[test]
+    fn lenient_fen() {
+        crate::init();
+
+        let fen = "4k3/8/8/8/8/8/8/4K3 b - - 0 0";
+        assert_eq!(Board::new(fen).unwrap().to_string(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+        assert!(Board::from_fen_strict(fen).is_err());
+
+        let fen = "4k3/8/8/8/8/8/8/4K3 w - - 300 70";
+        assert_eq!(Board::new(fen).unwrap().to_string(), "4k3/8/8/8/8/8/8/4K3 w - - 255 70");
+        assert!(Board::from_fen_strict(fen).is_err());
+
+        assert!(Board::new("4k3/8/8/8/8/8/8/4K3 w - - x 1").is_err());
+        assert!(Board::new("4k3/8/8/8/8/8/8/4K3 w - - 0 -1").is_err());
+
+        // The move counters may be omitted, but not just one of them.
+        let fen = "4k3/8/8/8/8/8/8/4K3 b - -";
+        assert_eq!(Board::new(fen).unwrap().to_string(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+        assert_eq!(Board::from_fen_strict(fen).unwrap().to_string(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+        let err = Board::new("4k3/8/8/8/8/8/8/4K3 w - - 0").unwrap_err();
+        assert!(err.to_string().contains("got 5"));
+        assert!(Board::new("4k3/8/8/8/8/8/8/4K3 w -").is_err());
+        assert!(Board::new("4k3/8/8/8/8/8/8/4K3 w - - 0 1 0").is_err());
+    }
+
+    #[test]
+    fn position_history() {
+        crate::init();
+
+        let mut board = Board::new("4k3/8/8/8/8/8/8/4K3 w - - 0 10").unwrap();
+        for mv in ["e1e2", "e8e7", "e2e1", "e7e8"] {
+            board.do_move(board.parse_move(mv).unwrap());
+        }
+
+        let history: Vec<_> = board.position_history().collect();
+        assert_eq!(history.len(), 5);
+        assert_eq!(history[0].zobrist, history[4].zobrist);
+        assert_ne!(history[0].zobrist, history[2].zobrist);
+        assert_eq!(history.iter().map(|pos| pos.fullmove).collect::<Vec<_>>(), [10, 10, 11, 11, 12]);
+        assert_eq!(history[1].side_to_move, Color::Black);
+    }
+
+    #[test]
+    fn epd() {
+        crate::init();
+
+        let epd = "4k3/8/8/8/8/8/8/4K2R w K - bm Rh8+; id \"test\";";
+        assert_eq!(Board::from_epd(epd).unwrap().to_string(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+
+        let epd = "4k3/8/8/8/8/8/8/4K2R b K - id \"test\"; hmvc 12; fmvn 40;";
+        assert_eq!(Board::from_epd(epd).unwrap().to_string(), "4k3/8/8/8/8/8/8/4K2R b K - 12 40");
+
+        assert!(Board::from_epd("4k3/8/8/8/8/8/8/4K2R w K - hmvc x;").is_err());
+        assert!(Board::from_epd("4k3/8/8/8/8/8/8/4K2R w K").is_err());
+    }
+
+    #[test]
+    fn pawn_shelter() {
+        crate::init();
+
+        // Intact shelter for white, and a king on the edge without pawns for black.
+        let board = Board::new("k7/8/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        assert_eq!(board.pawn_shelter(Color::White), 0);
+        assert_eq!(board.pawn_shelter(Color::Black), 72);
+
+        // The g pawn pushed twice, the h pawn missing, and a black pawn storming on the h file.
+        let board = Board::new("k7/8/8/8/6Pp/8/5P2/6K1 w - - 0 1").unwrap();
+        assert_eq!(board.pawn_shelter(Color::White), 20 + 36);
+        assert_eq!(board.pawn_storm(Color::White), 15);
+        assert_eq!(board.pawn_storm(Color::Black), 0);
+    }
+
+    #[test]
+    fn king_ring_pressure() {
+        crate::init();
+
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(board.king_ring_pressure(Color::White), 0);
+        assert_eq!(board.king_ring_pressure(Color::Black), 0);
+
+        // The greek gift setup: h7 hit by the bishop and the knight, f7 by the knight.
+        let board = Board::new("r1bq1rk1/pppn1ppp/4p3/3pP1N1/3P4/3B4/PPP2PPP/R2QK2R w KQ - 0 1").unwrap();
+        assert_eq!(board.king_ring_pressure(Color::Black), 3);
+        assert_eq!(board.king_ring_pressure(Color::White), 0);
+    }
+
+    #[test]
+    fn back_rank_weakness() {
+        crate::init();
+
+        // Both kings boxed in, only white has a rook to exploit it.
+        let board = Board::new("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+        assert!(board.back_rank_weakness(Color::Black));
+        assert!(!board.back_rank_weakness(Color::White));
+
+        // Luft on h6, or the king off the back rank.
+        assert!(!Board::new("6k1/5pp1/7p/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap().back_rank_weakness(Color::Black));
+        assert!(!Board::new("8/5ppk/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap().back_rank_weakness(Color::Black));
+
+        // The escape square h7 is covered by the bishop.
+        assert!(Board::new("6k1/5pp1/8/8/8/3B4/5PPP/4R1K1 w - - 0 1").unwrap().back_rank_weakness(Color::Black));
+    }
+
+    #[test]
+    fn packed() {
+        crate::init();
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/8/8/8/8/8/6k1/4K2R b K - 17 42",
+        ] {
+            let board = Board::new(fen).unwrap();
+            let unpacked = Board::from_packed(&board.to_packed()).unwrap();
+            assert_eq!(unpacked.to_string(), fen);
+            assert_eq!(unpacked.get_zobrist(), board.get_zobrist());
+        }
+
+        // More pieces than there are nibbles.
+        assert!(Board::from_packed(&[0xFF; 32]).is_err());
+    }
+
+    #[test]
+    fn incremental_zobrist() {
+        crate::init();
+
+        let mut board = Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        check_zobrist(&mut board, 3);
+    }
+
+    #[test]
+    fn null_move() {
+        crate::init();
+
+        for fen in &[
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ] {
+            let mut board = Board::new(fen).unwrap();
+            let start = board.clone();
+
+            board.do_null();
+            assert_eq!(board.get_side_to_move(), Color::Black);
+            assert_eq!(board.get_ep_square(), EnPassantSquare::None);
+            assert_eq!(board.get_castle_rights(), start.get_castle_rights());
+            assert_eq!(board.get_zobrist(), board.zobrist(), "mismatch on board: {}", board);
+
+            board.undo_null();
+            assert_eq!(board.to_string(), start.to_string());
+            assert_eq!(board.get_zobrist(), start.get_zobrist());
+        }
+    }
+
+    #[test]
+    fn random_games_zobrist() {
+        crate::init();
+
+        let mut seed = 0x9E37_79B9u32;
+        let mut rand = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+
+        for fen in &[
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ] {
+            for _ in 0..20 {
+                let mut board = Board::new(fen).unwrap();
+                let start = board.clone();
+                let mut played = Vec::new();
+
+                // Play random legal moves until the game ends or enough were played.
+                for _ in 0..200 {
+                    let mut moves = Vec::new();
+                    movegen::legals(&board, &mut moves);
+                    if moves.is_empty() {
+                        break;
                     }
+
+                    let mv = moves[rand() as usize % moves.len()];
+                    board.do_move(mv);
+                    played.push(mv);
+                    assert_eq!(board.get_zobrist(), board.zobrist(), "mismatch after {} on board: {}", mv, board);
                 }
-                
-                if x > 8 {
-                    return Err(Error::msg("Rank too large in fen string."));
+
+                // Undo everything, back to the start.
+                while let Some(mv) = played.pop() {
+                    board.undo_move(mv);
+                    assert_eq!(board.get_zobrist(), board.zobrist(), "mismatch after undoing {}", mv);
                 }
-            }
 
-            if x != 8 {
-                return Err(Error::msg("Rank too small in fen string."));
+                assert_eq!(board.to_string(), start.to_string());
+                assert_eq!(board.get_zobrist(), start.get_zobrist());
+                assert_eq!(board.to_packed(), start.to_packed());
             }
         }
+    }
 
-        // Check that both sides have only one king
-        for color in Color::COLORS {
-            if !board.get_bitboard(color, Piece::King).is_one() {
-                return Err(Error::msg("Invalid number of kings on the board."));
+    #[test]
+    fn eval_listener() {
+        crate::init();
+
+        for fen in &[
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ] {
+            let mut board = Board::new(fen).unwrap();
+            let mut mailbox = Mailbox([None; 64]);
+            for sq in (0..64).map(Square::from) {
+                mailbox.0[usize::from(sq)] = board.get_piece(sq);
             }
+
+            check_listener(&mut board, &mut mailbox, 3);
         }
+    }
 
-        // Compute the checkers of the board.
-        board.state.checkers = board.checkers();
-        // Compute the pinned pieces of the board.
-        board.state.pinned = board.pinned();
+    #[test]
+    fn rooks() {
+        crate::init();
+
+        // White doubled it's rooks on the seventh, black's defend each other along the a file.
+        let board = Board::new("6k1/1R3R2/8/8/8/8/r7/r5K1 w - - 0 1").unwrap();
+        assert_eq!(board.rooks_on_seventh(Color::White), 2);
+        assert_eq!(board.rooks_on_seventh(Color::Black), 1);
+        assert!(board.connected_rooks(Color::White));
+        assert!(board.connected_rooks(Color::Black));
+
+        // A knight stands between the rooks, and none of black's is left.
+        let board = Board::new("6k1/1R1n1R2/8/8/8/8/8/6K1 w - - 0 1").unwrap();
+        assert_eq!(board.rooks_on_seventh(Color::White), 2);
+        assert!(!board.connected_rooks(Color::White));
+        assert_eq!(board.rooks_on_seventh(Color::Black), 0);
+        assert!(!board.connected_rooks(Color::Black));
+
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(board.rooks_on_seventh(Color::White), 0);
+        assert!(!board.connected_rooks(Color::White));
+    }
 
-        // TODO: further checks ?
- 
-        Ok(board)
+    #[test]
+    fn bishop_pair() {
+        crate::init();
+
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert!(board.has_bishop_pair(Color::White));
+        assert!(board.has_bishop_pair(Color::Black));
+
+        // Bishops on squares of both colors make a pair, a single bishop does not.
+        let board = Board::new("4k1b1/8/8/8/8/8/8/2B1KB2 w - - 0 1").unwrap();
+        assert!(board.has_bishop_pair(Color::White));
+        assert!(!board.has_bishop_pair(Color::Black));
+
+        // White promoted a bishop on h7, on a light square like the one on f1.
+        let board = Board::new("4k3/7B/8/8/8/8/8/4KB2 w - - 0 1").unwrap();
+        assert!(!board.has_bishop_pair(Color::White));
+        assert!(!board.has_bishop_pair(Color::Black));
+    }
+
+    #[test]
+    fn chess960() {
+        crate::init();
+
+        // Perft results from the Chess960 positions of chessprogramming.org.
+        for (fen, count) in [
+            ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", 12189),
+            ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", 18002),
+            ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", 10471),
+        ] {
+            let mut board = Board::from_fen_960(fen).unwrap();
+            assert_eq!(board.to_string(), fen);
+            assert_eq!(movegen::perft(&mut board, 3), count, "{}", fen);
+            check_zobrist(&mut board, 2);
+
+            movegen::for_each_position(&[fen], |board, legals| {
+                for &mv in legals {
+                    let gives_check = board.gives_check(mv);
+                    board.do_move(mv);
+                    assert_eq!(gives_check, board.get_checkers().not_empty(), "{} {}", board, mv);
+                    board.undo_move(mv);
+                }
+            });
+        }
+
+        // The king and rook may start next to each other, the king may not move at all,
+        // and the rook may land on the square the king left.
+        for (fen, mv, after) in [
+            ("4k3/8/8/8/8/8/8/RK5R w HA - 0 1", "b1a1", "4k3/8/8/8/8/8/8/2KR3R b - - 0 1"),
+            ("4k3/8/8/8/8/8/8/RK5R w HA - 0 1", "b1h1", "4k3/8/8/8/8/8/8/R4RK1 b - - 0 1"),
+            ("4k3/8/8/8/8/8/8/6KR w H - 0 1", "g1h1", "4k3/8/8/8/8/8/8/5RK1 b - - 0 1"),
+            ("4k3/8/8/8/8/8/8/5K1R w H - 0 1", "f1h1", "4k3/8/8/8/8/8/8/5RK1 b - - 0 1"),
+        ] {
+            let mut board = Board::from_fen_960(fen).unwrap();
+            let mv = board.parse_move(mv).unwrap();
+            assert!(mv.is_castle());
+
+            board.do_move(mv);
+            assert_eq!(board.to_string(), after);
+            assert_eq!(board.get_zobrist(), board.zobrist());
+            board.undo_move(mv);
+            assert_eq!(board.to_string(), fen);
+        }
+
+        // A rook shielding the king's destination can't be relied upon.
+        let board = Board::from_fen_960("4k3/8/8/8/8/8/8/r1RK4 w C - 0 1").unwrap();
+        assert!(board.parse_move("d1c1").is_err());
+
+        // X-fen letters designate the outermost rooks, the standard setup is displayed as usual.
+        let board = Board::from_fen_960("4k3/8/8/8/8/8/8/1RR1K1R1 w KQ - 0 1").unwrap();
+        assert_eq!(board.to_string(), "4k3/8/8/8/8/8/8/1RR1K1R1 w GB - 0 1");
+        let board = Board::from_fen_960("r3k2r/8/8/8/8/8/8/R3K2R w HAa - 0 1").unwrap();
+        assert_eq!(board.to_string(), "r3k2r/8/8/8/8/8/8/R3K2R w KQq - 0 1");
+
+        // Every legal move is parsed back from its uci notation, castles being written as the king
+        // taking it's own rook, even when the king doesn't move or moves a single square.
+        for (fen, castle) in [
+            ("4k3/8/8/8/8/8/8/6KR w H - 0 1", "g1h1"),
+            ("4k3/8/8/8/8/8/8/RK6 w A - 0 1", "b1a1"),
+            ("4k3/8/8/8/8/8/8/R4K1R w HA - 0 1", "f1h1"),
+            ("1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1", "e1b1"),
+        ] {
+            let board = Board::from_fen_960(fen).unwrap();
+            let mut castles = Vec::new();
+
+            movegen::gen_legals(&board, |mv| {
+                assert_eq!(board.parse_move(&mv.to_string()).unwrap(), mv, "{} {}", fen, mv);
+                if mv.is_castle() {
+                    castles.push(mv.to_string());
+                }
+            });
+
+            assert!(castles.iter().any(|mv| mv == castle), "{}", fen);
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn move_to_san() {
+        crate::init();
+
+        for (fen, mv, san) in [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "g1f3", "Nf3"),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "e2e4", "e4"),
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "e1g1", "O-O"),
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "e1c1", "O-O-O"),
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "d5e6", "dxe6"),
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "e5f7", "Nxf7"),
+            ("4k3/8/8/R7/8/8/8/RN2KN2 w - - 0 1", "b1d2", "Nbd2"),
+            ("4k3/8/8/R7/8/8/8/RN2KN2 w - - 0 1", "a1a3", "R1a3"),
+            ("8/2k5/8/8/4Q2Q/8/8/K6Q w - - 0 1", "h4e1", "Qh4e1"),
+            ("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q", "a8=Q+"),
+            ("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8n", "a8=N"),
+            ("r5k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8", "Rxa8#"),
+            ("8/8/8/k2pP2R/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6+"),
+        ] {
+            let board = Board::new(fen).unwrap();
+            assert_eq!(board.move_to_san(board.parse_move(mv).unwrap()), san, "{}", fen);
+        }
+    }
+}
@@ -1,15 +1,19 @@
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fs;
 use std::path::Path;
+use std::str::FromStr;
 
 use anyhow::{Error, Result};
+use serde::{Deserialize, Serialize};
 
 use crate::attacks;
-use crate::board::Board;
+use crate::board::{Board, STARTPOS_FEN};
 use crate::color::Color;
 use crate::castle_rights::CastleMask;
 use crate::en_passant::EnPassantSquare;
 use crate::moves::Move;
+use crate::pgn::PgnReader;
 use crate::piece::Piece;
 use crate::square::Square;
 
@@ -217,7 +221,7 @@ const POLYGLOT_HASHING: [u64; 781] = [
 
 /// Compute the polyglot zobrist hash of the given position.
 #[inline]
-fn polyglot_hash(board: &Board) -> u64 {
+pub(crate) fn polyglot_hash(board: &Board) -> u64 {
     let mut hash = 0;
 
     // Pieces.
@@ -253,8 +257,11 @@ fn polyglot_hash(board: &Board) -> u64 {
     if let EnPassantSquare::Some(sq) = board.get_ep_square() {
         let us = board.get_side_to_move();
         let them = board.get_other_side();
+
+        // The board stores the square of the pawn, we need the target square.
+        let target = attacks::pawn_push(us, sq).unwrap();
         
-        if (attacks::pawn(them, sq) & board.get_bitboard(us, Piece::Pawn)).not_empty() {
+        if (attacks::pawn(them, target) & board.get_bitboard(us, Piece::Pawn)).not_empty() {
             hash ^= POLYGLOT_HASHING[772 + sq.x() as usize];
         }
     }
@@ -378,6 +385,117 @@ impl Book {
     }
 }
 
+//#################################################################################################
+//
+//                                       struct OpeningBook
+//
+//#################################################################################################
+
+/// A lightweight in-memory opening book, built from a collection of games: each
+/// position met in the first plies of the games is associated with the moves that
+/// were played from it, weighted by the number of times they were played.
+/// It may be saved and loaded in any format supported by serde.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OpeningBook {
+    entries: HashMap<u64, Vec<(Move, u32)>>,
+}
+
+// ================================ pub impl
+
+impl OpeningBook {
+    /// Builds a book from games given as lists of moves in uci notation separated by spaces,
+    /// all starting from the initial position. Only the first max_ply plies of each game are used.
+    #[cold]
+    pub fn from_games<'a>(games: impl IntoIterator<Item = &'a str>, max_ply: usize) -> Result<OpeningBook> {
+        let start = Board::from_str(STARTPOS_FEN)?;
+        let mut book = OpeningBook::default();
+
+        for (i, game) in games.into_iter().enumerate() {
+            let mut board = start.clone();
+            let mut moves = Vec::new();
+
+            for s in game.split_whitespace().take(max_ply) {
+                let mv = board.parse_move(s).map_err(|err| Error::msg(format!("Game #{}, move {:?}: {}", i, s, err)))?;
+                board.do_move(mv);
+                moves.push(mv);
+            }
+
+            book.add_game(&start, &moves, max_ply);
+        }
+
+        Ok(book)
+    }
+
+    /// Builds a book from the games of a pgn file, played from the position of their "FEN"
+    /// tag or from the initial position. Only the first max_ply plies of each game are used.
+    #[cold]
+    pub fn from_pgn(corpus: &str, max_ply: usize) -> Result<OpeningBook> {
+        let mut book = OpeningBook::default();
+
+        for game in PgnReader::parse_games(corpus)? {
+            book.add_game(game.get_board(), game.get_moves(), max_ply);
+        }
+
+        Ok(book)
+    }
+
+    /// Adds the first max_ply moves of a game to the book, played from the given board.
+    /// The moves are assumed to be legal.
+    pub fn add_game(&mut self, board: &Board, moves: &[Move], max_ply: usize) {
+        let mut board = board.clone();
+
+        for &mv in moves.iter().take(max_ply) {
+            let entry = self.entries.entry(board.get_zobrist().get_raw()).or_default();
+
+            match entry.iter_mut().find(|(other, _)| *other == mv) {
+                Some((_, weight)) => *weight += 1,
+                None => entry.push((mv, 1)),
+            }
+
+            board.do_move(mv);
+        }
+    }
+
+    /// Probes the book and returns the moves played from that position with their weights.
+    pub fn probe(&self, board: &Board) -> Box<[(Move, u32)]> {
+        match self.entries.get(&board.get_zobrist().get_raw()) {
+            Some(entry) => entry.iter().copied().filter(|&(mv, _)| board.is_pseudo_legal(mv) && board.is_legal(mv)).collect(),
+            None => Box::new([]),
+        }
+    }
+
+    /// Picks one of the moves of the book for that position, at random with respect to their
+    /// weights. The random number is given by the caller, so that the choice is reproducible.
+    pub fn pick(&self, board: &Board, rand: u32) -> Option<Move> {
+        let results = self.probe(board);
+        let total_weight: u32 = results.iter().map(|&(_, weight)| weight).sum();
+
+        if total_weight == 0 {
+            return None;
+        }
+
+        let mut rand = rand % total_weight;
+        for &(mv, weight) in results.iter() {
+            if rand < weight {
+                return Some(mv);
+            }
+            rand -= weight;
+        }
+
+        unreachable!()
+    }
+
+    /// Returns the number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the book is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
 //#################################################################################################
 //
 //                                              tests
@@ -387,6 +505,9 @@ impl Book {
 #[cfg(test)]
 mod tests {
     use crate::board::Board;
+    use crate::moves::Move;
+
+    use super::OpeningBook;
 
     const FEN_HASHES: &[(&str, u64)] = &[
         ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0x463b96181691fc9c),
@@ -400,12 +521,81 @@ mod tests {
         ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4", 0x5c3f9b829b279560),
     ];
 
+    #[test]
+    fn opening_book() {
+        crate::init();
+
+        let book = super::OpeningBook::from_games(["e2e4 e7e5 g1f3", "e2e4 c7c5", "d2d4 d7d5", "e2e4 e7e5 f1c4"], 2).unwrap();
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+
+        let mut moves: Vec<_> = book.probe(&board).iter().map(|&(mv, weight)| (mv.to_string(), weight)).collect();
+        moves.sort();
+        assert_eq!(moves, [("d2d4".to_string(), 1), ("e2e4".to_string(), 3)]);
+
+        // Only the first two plies of each game are in the book.
+        assert_eq!(book.len(), 3);
+        assert_eq!(book.pick(&board, 0).unwrap().to_string(), "e2e4");
+        assert!(super::OpeningBook::from_games(["e2e5"], 2).is_err());
+    }
+
+    #[test]
+    fn opening_book_pgn() {
+        crate::init();
+
+        let corpus = r#"[Event "First"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[Event "Second"]
+[Result "0-1"]
+
+1. e4 {The sicilian.} c5 2. Nf3 0-1
+
+[Event "Third"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1. e4 Kd7 *
+"#;
+
+        let book = OpeningBook::from_pgn(corpus, 2).unwrap();
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(book.probe(&board).iter().map(|&(mv, weight)| (mv.to_string(), weight)).collect::<Vec<_>>(), [("e2e4".to_string(), 2)]);
+
+        // The games are replayed from their own starting position.
+        let board = Board::new("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+        assert_eq!(book.probe(&board).iter().map(|&(mv, weight)| (mv.to_string(), weight)).collect::<Vec<_>>(), [("e8d7".to_string(), 1)]);
+        assert_eq!(book.len(), 4);
+
+        // An illegal move is reported with its game.
+        let err = OpeningBook::from_pgn(&corpus.replace("Kd7", "Kd6"), 2).unwrap_err();
+        assert!(err.to_string().starts_with("Game #2"), "{}", err);
+
+        // The book is saved and loaded back with serde.
+        let json = serde_json::to_string(&book).unwrap();
+        let loaded: OpeningBook = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.len(), book.len());
+        assert_eq!(loaded.probe(&board), book.probe(&board));
+
+        // Raw values that are not moves are rejected.
+        assert!(serde_json::from_str::<Move>("0").is_err());
+        assert!(serde_json::from_str::<Move>(&u32::MAX.to_string()).is_err());
+    }
+
     #[test]
     fn polyglot_hash() {
         crate::init();
 
         for &(fen, hash) in FEN_HASHES {
             assert_eq!(super::polyglot_hash(&Board::new(fen).unwrap()), hash, "mismatch on fen: {:?}", fen);
+            assert_eq!(Board::new(fen).unwrap().polyglot_key(), hash, "mismatch on fen: {:?}", fen);
+        }
+
+        // The same positions, reached by playing the moves.
+        let mut board = Board::new(FEN_HASHES[0].0).unwrap();
+        for (mv, &(_, hash)) in ["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"].iter().zip(&FEN_HASHES[1..]) {
+            board.do_move(board.parse_move(mv).unwrap());
+            assert_eq!(super::polyglot_hash(&board), hash, "mismatch after move: {}", mv);
         }
     }
 }
\ No newline at end of file
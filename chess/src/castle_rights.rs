@@ -1,8 +1,10 @@
+use std::cmp::Ordering;
 use std::fmt;
 use std::str::FromStr;
 
 use anyhow::{Error, Result};
 
+use crate::color::Color;
 use crate::square::Square;
 
 //#################################################################################################
@@ -21,6 +23,149 @@ pub enum CastleMask {
     BlackOOO = 0b1000,
 }
 
+// ================================ pub impl
+
+impl CastleMask {
+    /// Returns the mask of the castle of that color, on that side.
+    #[inline]
+    pub fn new(color: Color, side: CastleSide) -> CastleMask {
+        match (color, side) {
+            (Color::White, CastleSide::King) => CastleMask::WhiteOO,
+            (Color::White, CastleSide::Queen) => CastleMask::WhiteOOO,
+            (Color::Black, CastleSide::King) => CastleMask::BlackOO,
+            (Color::Black, CastleSide::Queen) => CastleMask::BlackOOO,
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                       enum CastleSide
+//
+//#################################################################################################
+
+/// The side of the board a castle happens on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CastleSide {
+    /// Castling short, with the rook on the h file side of the king.
+    King,
+    /// Castling long, with the rook on the a file side of the king.
+    Queen,
+}
+
+// ================================ pub impl
+
+impl CastleSide {
+    /// Both sides, king side first.
+    pub const SIDES: [CastleSide; 2] = [
+        CastleSide::King, CastleSide::Queen,
+    ];
+
+    /// Returns the square the king of that color lands on when castling on that side,
+    /// in standard chess as in Chess960: on the g or c file.
+    #[inline]
+    pub fn king_to(self, color: Color) -> Square {
+        match (color, self) {
+            (Color::White, CastleSide::King) => Square::G1,
+            (Color::White, CastleSide::Queen) => Square::C1,
+            (Color::Black, CastleSide::King) => Square::G8,
+            (Color::Black, CastleSide::Queen) => Square::C8,
+        }
+    }
+
+    /// Returns the square the rook of that color lands on when castling on that side,
+    /// in standard chess as in Chess960: on the f or d file.
+    #[inline]
+    pub fn rook_to(self, color: Color) -> Square {
+        match (color, self) {
+            (Color::White, CastleSide::King) => Square::F1,
+            (Color::White, CastleSide::Queen) => Square::D1,
+            (Color::Black, CastleSide::King) => Square::F8,
+            (Color::Black, CastleSide::Queen) => Square::D8,
+        }
+    }
+
+    /// Returns the color and side of the castle whose king lands on that square,
+    /// or None if no castle does.
+    #[inline]
+    pub fn from_king_to(king_to: Square) -> Option<(Color, CastleSide)> {
+        match king_to {
+            Square::G1 => Some((Color::White, CastleSide::King)),
+            Square::C1 => Some((Color::White, CastleSide::Queen)),
+            Square::G8 => Some((Color::Black, CastleSide::King)),
+            Square::C8 => Some((Color::Black, CastleSide::Queen)),
+            _ => None,
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                      struct CastleSetup
+//
+//#################################################################################################
+
+/// The starting squares of the kings and of the rooks they castle with. Always the same in
+/// standard chess, they vary in Chess960 where the back rank is shuffled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CastleSetup {
+    kings: [Square; 2],
+    rooks: [[Square; 2]; 2],
+}
+
+// ================================ pub impl
+
+impl CastleSetup {
+    /// The setup of standard chess: kings on the e file, rooks on the a and h files.
+    pub const STANDARD: CastleSetup = CastleSetup {
+        kings: [Square::E1, Square::E8],
+        rooks: [[Square::H1, Square::A1], [Square::H8, Square::A8]],
+    };
+
+    /// Returns the starting square of the king of that color.
+    #[inline]
+    pub fn king(&self, color: Color) -> Square {
+        self.kings[usize::from(color)]
+    }
+
+    /// Returns the starting square of the rook of that color castling on that side.
+    #[inline]
+    pub fn rook(&self, color: Color, side: CastleSide) -> Square {
+        self.rooks[usize::from(color)][side as usize]
+    }
+
+    /// Returns true if this is the setup of standard chess.
+    #[inline]
+    pub fn is_standard(&self) -> bool {
+        *self == CastleSetup::STANDARD
+    }
+}
+
+// ================================ pub(crate) impl
+
+impl CastleSetup {
+    /// Sets the starting square of the king of that color.
+    #[inline]
+    pub(crate) fn set_king(&mut self, color: Color, sq: Square) {
+        self.kings[usize::from(color)] = sq;
+    }
+
+    /// Sets the starting square of the rook of that color castling on that side.
+    #[inline]
+    pub(crate) fn set_rook(&mut self, color: Color, side: CastleSide, sq: Square) {
+        self.rooks[usize::from(color)][side as usize] = sq;
+    }
+}
+
+// ================================ traits impl
+
+impl Default for CastleSetup {
+    /// Returns CastleSetup::STANDARD.
+    fn default() -> CastleSetup {
+        CastleSetup::STANDARD
+    }
+}
+
 //#################################################################################################
 //
 //                                      struct CastleRights
@@ -34,13 +179,61 @@ pub struct CastleRights(u8);
 // ================================ pub impl
 
 impl CastleRights {
+    /// No castle rights at all.
+    pub const NONE: CastleRights = CastleRights(0b0000);
+
     /// Returns true if those rights contain that mask.
     #[inline]
     pub fn has(self, mask: CastleMask) -> bool {
         (self.0 & mask as u8) != 0
     }
 
-    /// Updates the rights with the given from and to squares of the move.
+    /// Returns true if the player of that color may castle on that side.
+    #[inline]
+    pub fn can(self, color: Color, side: CastleSide) -> bool {
+        self.has(CastleMask::new(color, side))
+    }
+
+    /// Returns those rights, with the right of the player of that color to castle
+    /// on that side given or taken away.
+    #[inline]
+    pub fn set(mut self, color: Color, side: CastleSide, value: bool) -> CastleRights {
+        let mask = CastleMask::new(color, side) as u8;
+
+        if value {
+            self.0 |= mask;
+        } else {
+            self.0 &= !mask;
+        }
+
+        self
+    }
+
+    /// Updates the rights with the given from and to squares of the move, in a game
+    /// with that castle setup: moving the king or a rook, or capturing a rook, loses
+    /// the rights that depend on it.
+    #[inline]
+    pub fn update_with(&mut self, setup: &CastleSetup, from: Square, to: Square) {
+        if setup.is_standard() {
+            return self.update(from, to);
+        }
+
+        for color in Color::COLORS {
+            if from == setup.king(color) {
+                self.remove(CastleMask::new(color, CastleSide::King));
+                self.remove(CastleMask::new(color, CastleSide::Queen));
+            }
+
+            for side in CastleSide::SIDES {
+                let rook = setup.rook(color, side);
+                if from == rook || to == rook {
+                    self.remove(CastleMask::new(color, side));
+                }
+            }
+        }
+    }
+
+    /// Updates the rights with the given from and to squares of the move, in standard chess.
     #[inline]
     pub fn update(&mut self, from: Square, to: Square) {
         match from {
@@ -73,6 +266,88 @@ impl CastleRights {
     pub fn raw(self) -> u8 {
         self.0
     }
+
+    /// Parses castle rights in fen (KQkq) or Shredder-fen (HAha) notation, the kings standing
+    /// on their squares of the setup. In Shredder-fen, each letter is the file of the rook to
+    /// castle with, uppercase for white: that rook is recorded in the setup, on the side of the
+    /// king it stands. K, Q, k and q keep the rooks already in the setup. Both may be mixed.
+    pub fn parse_with(s: &str, setup: &mut CastleSetup) -> Result<CastleRights> {
+        if s == "-" {
+            return Ok(CastleRights::NONE);
+        }
+
+        if s.is_empty() || s.len() > 4 {
+            return Err(Error::msg("Invalid castle rights format"));
+        }
+
+        let mut rights = CastleRights::NONE;
+
+        for c in s.chars() {
+            let color = if c.is_ascii_uppercase() {Color::White} else {Color::Black};
+
+            let side = match c.to_ascii_lowercase() {
+                'k' => CastleSide::King,
+                'q' => CastleSide::Queen,
+                file @ 'a'..='h' => {
+                    let king = setup.king(color);
+                    let rook = Square::from(((file as u8 - b'a') as i8, king.y()));
+
+                    let side = match rook.x().cmp(&king.x()) {
+                        Ordering::Greater => CastleSide::King,
+                        Ordering::Less => CastleSide::Queen,
+                        Ordering::Equal => return Err(Error::msg("Invalid castle rights format")),
+                    };
+
+                    setup.set_rook(color, side, rook);
+                    side
+                },
+                _ => return Err(Error::msg("Invalid castle rights format")),
+            };
+
+            if rights.can(color, side) {
+                return Err(Error::msg("Invalid castle rights format"));
+            }
+            rights = rights.set(color, side, true);
+        }
+
+        Ok(rights)
+    }
+
+    /// Returns the rights in fen notation (KQkq) if the setup is the one of standard chess,
+    /// and in Shredder-fen notation (HAha) otherwise.
+    pub fn to_string_with(self, setup: &CastleSetup) -> String {
+        if setup.is_standard() || self == CastleRights::NONE {
+            return self.to_string();
+        }
+
+        let mut s = String::with_capacity(4);
+
+        for color in Color::COLORS {
+            for side in CastleSide::SIDES {
+                if self.can(color, side) {
+                    let file = (b'a' + setup.rook(color, side).x() as u8) as char;
+                    s.push(if color == Color::White {file.to_ascii_uppercase()} else {file});
+                }
+            }
+        }
+
+        s
+    }
+}
+
+// ================================ pub(crate) impl
+
+impl CastleRights {
+    /// Creates castle rights from their raw representation, as returned by raw().
+    /// Returns None if the raw value is out of range.
+    #[inline]
+    pub(crate) fn from_raw(raw: u8) -> Option<CastleRights> {
+        if raw < 16 {
+            Some(CastleRights(raw))
+        } else {
+            None
+        }
+    }
 }
 
 // ================================ impl
@@ -122,8 +397,20 @@ impl fmt::Display for CastleRights {
 impl<'a> FromStr for CastleRights {
     type Err = Error;
 
-    /// From fen notation for castle rights.
+    /// From fen notation for castle rights. The Shredder-fen notation is accepted as
+    /// well, for the rooks of standard chess only (HAha): see CastleRights::parse_with().
     fn from_str(s: &str) -> Result<CastleRights> {
+        if s.chars().any(|c| ('a'..='h').contains(&c.to_ascii_lowercase())) {
+            let mut setup = CastleSetup::STANDARD;
+            let rights = CastleRights::parse_with(s, &mut setup)?;
+
+            return if setup.is_standard() {
+                Ok(rights)
+            } else {
+                Err(Error::msg("Invalid castle rights format"))
+            };
+        }
+
         Ok(CastleRights(match s {
             "-"    => 0b0000,
             "K"    => 0b0001,
@@ -144,4 +431,57 @@ impl<'a> FromStr for CastleRights {
             _ => return Err(Error::msg("Invalid castle rights format")),
         }))
     }
-}
\ No newline at end of file
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::castle_rights::{CastleRights, CastleSetup, CastleSide};
+    use crate::color::Color;
+    use crate::square::Square;
+
+    #[test]
+    fn flags() {
+        let rights = CastleRights::NONE
+            .set(Color::White, CastleSide::King, true)
+            .set(Color::Black, CastleSide::Queen, true);
+        assert_eq!(rights.to_string(), "Kq");
+        assert!(rights.can(Color::White, CastleSide::King));
+        assert!(!rights.can(Color::White, CastleSide::Queen));
+
+        let rights = CastleRights::from_str("KQkq").unwrap().set(Color::Black, CastleSide::King, false);
+        assert_eq!(rights.to_string(), "KQq");
+        assert_eq!(rights.set(Color::Black, CastleSide::King, true), CastleRights::default());
+    }
+
+    #[test]
+    fn shredder() {
+        // Standard rooks, written as files.
+        assert_eq!(CastleRights::from_str("HAha").unwrap(), CastleRights::default());
+        assert_eq!(CastleRights::from_str("Hq").unwrap().to_string(), "Kq");
+        assert!(CastleRights::from_str("Ga").is_err());
+        assert!(CastleRights::from_str("HH").is_err());
+
+        // A Chess960 setup, with the white king on b1 and the black one on g8.
+        let mut setup = CastleSetup::STANDARD;
+        setup.set_king(Color::White, Square::B1);
+        setup.set_king(Color::Black, Square::G8);
+
+        let rights = CastleRights::parse_with("GAf", &mut setup).unwrap();
+        assert_eq!(setup.rook(Color::White, CastleSide::King), Square::G1);
+        assert_eq!(setup.rook(Color::White, CastleSide::Queen), Square::A1);
+        assert_eq!(setup.rook(Color::Black, CastleSide::Queen), Square::F8);
+        assert!(!rights.can(Color::Black, CastleSide::King));
+        assert_eq!(rights.to_string_with(&setup), "GAf");
+        assert_eq!(rights.to_string_with(&CastleSetup::STANDARD), "KQq");
+
+        assert!(CastleRights::parse_with("B", &mut setup).is_err());
+    }
+}
@@ -11,7 +11,8 @@ use crate::square::Square;
 //
 //#################################################################################################
 
-/// Keeps track off the en passant target square.
+/// Keeps track off the en passant square. In fen, it is the target square of the capture,
+/// while the Board stores the square of the pawn that may be captured.
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum EnPassantSquare {
@@ -0,0 +1,109 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Error, Result};
+
+use crate::bitboard::BitBoard;
+use crate::board::Board;
+use crate::color::Color;
+use crate::piece::Piece;
+
+//#################################################################################################
+//
+//                                     struct ParseFenError
+//
+//#################################################################################################
+
+/// The error returned when a fen string in a batch can't be parsed, or describes
+/// an impossible position.
+#[derive(Clone, Debug)]
+pub struct ParseFenError {
+    index: usize,
+    msg: String,
+}
+
+// ================================ pub impl
+
+impl ParseFenError {
+    /// Returns the index of the faulty fen string in the batch.
+    #[inline]
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the message describing the error.
+    #[inline]
+    pub fn get_msg(&self) -> &str {
+        &self.msg
+    }
+}
+
+// ================================ traits impl
+
+impl fmt::Display for ParseFenError {
+    /// Displays the index of the fen string along with the error.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fen #{}: {}", self.index, self.msg)
+    }
+}
+
+impl error::Error for ParseFenError {}
+
+//#################################################################################################
+//
+//                                          functions
+//
+//#################################################################################################
+
+/// Parses the fen string and checks that the resulting position is legal.
+pub fn validate(fen: &str) -> Result<Board> {
+    let board = Board::from_str(fen)?;
+
+    let pawns = board.get_bitboard(Color::White, Piece::Pawn) | board.get_bitboard(Color::Black, Piece::Pawn);
+    if (pawns & (BitBoard::RANK_1 | BitBoard::RANK_8)).not_empty() {
+        return Err(Error::msg("Pawns on the first or last rank."));
+    }
+
+    if !board.is_position_legal() {
+        return Err(Error::msg("The side not to move is in check."));
+    }
+
+    Ok(board)
+}
+
+/// Validates each of the fen strings, without stopping at the first error.
+/// The i-th result corresponds to the i-th fen string.
+pub fn validate_many(lines: &[&str]) -> Vec<Result<(), ParseFenError>> {
+    lines.iter().enumerate().map(|(index, fen)| {
+        validate(fen).map(|_| ()).map_err(|err| ParseFenError {
+            index,
+            msg: err.to_string(),
+        })
+    }).collect()
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn validate_many() {
+        crate::init();
+
+        let results = super::validate_many(&[
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN w KQkq - 0 1",
+            "4k3/8/8/8/8/8/8/4K2R w - - 0 1",
+            "4k2R/8/8/8/8/8/8/4K3 w - - 0 1",
+            "P3k3/8/8/8/8/8/8/4K3 b - - 0 1",
+        ]);
+
+        let failed: Vec<_> = results.iter().filter_map(|res| res.as_ref().err()).map(|err| err.get_index()).collect();
+        assert_eq!(failed, [1, 3, 4]);
+    }
+}
@@ -11,13 +11,18 @@ mod attacks;
 pub mod castle_rights;
 pub mod en_passant;
 mod cuckoo;
+pub mod pawns;
 
 // Board type.
 pub mod board;
 pub mod movegen;
+pub mod move_list;
 
 // Utils.
+pub mod adjudicator;
 pub mod book;
+pub mod fen;
+pub mod pgn;
 
 pub mod prelude {
     pub use crate::board::{Board, Status}; 
@@ -40,5 +45,36 @@ pub fn init() {
         zobrist::init();
         attacks::init();
         cuckoo::init();
+        pawns::init();
     });
-}
\ No newline at end of file
+}
+
+/// Checks the move generator against the known perft results of the six standard test
+/// positions, to a modest depth. Meant to be called at startup (behind a debug flag) to catch a
+/// miscompilation or a platform specific bug, e.g. in the magic or pext paths of wasm or cross
+/// compiled builds. Initializes the lib, and returns a description of the first mismatch if any.
+#[cold]
+pub fn self_test() -> Result<(), String> {
+    // The positions and their perft results at depth 3.
+    const POSITIONS: [(&str, u64); 6] = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 8902),
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 97862),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 2812),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 9467),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 62379),
+        ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", 89890),
+    ];
+
+    init();
+
+    for (fen, expected) in POSITIONS {
+        let mut board = board::Board::new(fen).map_err(|e| format!("Unable to parse {:?}: {}", fen, e))?;
+        let nodes = movegen::perft(&mut board, 3);
+
+        if nodes != expected {
+            return Err(format!("Perft mismatch at depth 3 for {:?}: {} nodes instead of {}.", fen, nodes, expected));
+        }
+    }
+
+    Ok(())
+}
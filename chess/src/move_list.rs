@@ -0,0 +1,218 @@
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+
+use crate::board::Board;
+use crate::movegen;
+use crate::moves::Move;
+
+/// The maximum number of moves a MoveList can hold. No chess position has more
+/// than 218 legal moves.
+pub const MAX_MOVES: usize = 256;
+
+//#################################################################################################
+//
+//                                         struct MoveList
+//
+//#################################################################################################
+
+/// A list of moves backed by a fixed-size array, that can be filled without any heap allocation.
+/// Each move carries a score (0 unless told otherwise), used to order them: either by sorting the
+/// whole list, or by picking the best remaining move each time, which is cheaper when only the
+/// first few moves are likely to be looked at. Dereferences to a slice of the moves.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [MaybeUninit<Move>; MAX_MOVES],
+    scores: [i32; MAX_MOVES],
+    len: usize,
+    picked: usize,
+}
+
+// ================================ pub impl
+
+impl MoveList {
+    /// Creates a new, empty list.
+    #[inline]
+    pub fn new() -> MoveList {
+        MoveList {
+            moves: [MaybeUninit::uninit(); MAX_MOVES],
+            scores: [0; MAX_MOVES],
+            len: 0,
+            picked: 0,
+        }
+    }
+
+    /// Creates a list filled with the legal moves of the board.
+    #[inline]
+    pub fn legals(board: &Board) -> MoveList {
+        let mut list = MoveList::new();
+        movegen::fill_legals(board, &mut list);
+        list
+    }
+
+    /// Pushes a move at the end of the list, with a score of 0. Panics if the list is full.
+    #[inline]
+    pub fn push(&mut self, mv: Move) {
+        self.push_scored(mv, 0);
+    }
+
+    /// Pushes a move at the end of the list, with the given score. Panics if the list is full.
+    #[inline]
+    pub fn push_scored(&mut self, mv: Move, score: i32) {
+        assert!(self.len < MAX_MOVES, "MoveList is full");
+        self.moves[self.len] = MaybeUninit::new(mv);
+        self.scores[self.len] = score;
+        self.len += 1;
+    }
+
+    /// Returns the score of the move at that index.
+    #[inline]
+    pub fn get_score(&self, i: usize) -> i32 {
+        self.scores[..self.len][i]
+    }
+
+    /// Sets the score of the move at that index.
+    #[inline]
+    pub fn set_score(&mut self, i: usize, score: i32) {
+        self.scores[..self.len][i] = score;
+    }
+
+    /// Scores every move of the list with the given function (MVV-LVA, history, ...).
+    #[inline]
+    pub fn score_with(&mut self, mut score: impl FnMut(Move) -> i32) {
+        for i in 0..self.len {
+            self.scores[i] = score(self[i]);
+        }
+    }
+
+    /// Sorts the moves by decreasing score. The sort is stable, and resets picking.
+    pub fn sort_by_score(&mut self) {
+        // An insertion sort, lists are short.
+        for i in 1..self.len {
+            let mut j = i;
+            while j > 0 && self.scores[j - 1] < self.scores[j] {
+                self.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+
+        self.picked = 0;
+    }
+
+    /// Returns the move with the highest score among those not picked yet, along with its score,
+    /// or None if they were all picked. Picked moves are moved to the front of the list, in the
+    /// order they were picked.
+    pub fn pick_best(&mut self) -> Option<(Move, i32)> {
+        if self.picked == self.len {
+            return None;
+        }
+
+        // Find the first of the best remaining moves and bring it to the front.
+        let mut best = self.picked;
+        for i in self.picked + 1..self.len {
+            if self.scores[i] > self.scores[best] {
+                best = i;
+            }
+        }
+
+        self.swap(self.picked, best);
+        self.picked += 1;
+
+        Some((self[self.picked - 1], self.scores[self.picked - 1]))
+    }
+
+    /// Removes all moves from the list.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.picked = 0;
+    }
+}
+
+// ================================ impl
+
+impl MoveList {
+    /// Swaps the moves and scores at those indices.
+    #[inline]
+    fn swap(&mut self, i: usize, j: usize) {
+        self.moves.swap(i, j);
+        self.scores.swap(i, j);
+    }
+}
+
+// ================================ traits impl
+
+impl Default for MoveList {
+    /// Returns an empty list.
+    fn default() -> MoveList {
+        MoveList::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        // SAFE: the first len moves were initialized by push_scored().
+        unsafe {&*(&self.moves[..self.len] as *const [MaybeUninit<Move>] as *const [Move])}
+    }
+}
+
+impl fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter().zip(&self.scores[..self.len])).finish()
+    }
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::move_list::MoveList;
+    use crate::movegen;
+
+    #[test]
+    fn legals() {
+        crate::init();
+
+        let board = Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut buffer = Vec::new();
+        movegen::legals(&board, &mut buffer);
+
+        let mut list = MoveList::legals(&board);
+        assert_eq!(&list[..], &buffer[..]);
+
+        list.clear();
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn scores() {
+        crate::init();
+
+        let board = Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+
+        // Captures first, the most valuable victims first.
+        let mut list = MoveList::legals(&board);
+        list.score_with(|mv| if mv.is_capture() {1 + mv.get_capture() as i32} else {0});
+
+        let mut picked = Vec::new();
+        while let Some((mv, score)) = list.pick_best() {
+            picked.push((mv, score));
+        }
+        assert_eq!(picked.len(), list.len());
+        assert!(picked.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert!(picked.iter().zip(list.iter()).all(|(&(mv1, _), &mv2)| mv1 == mv2));
+        assert!(list.pick_best().is_none());
+
+        list.set_score(list.len() - 1, 100);
+        list.sort_by_score();
+        assert_eq!(list.get_score(0), 100);
+        assert_eq!(list.pick_best().unwrap().1, 100);
+    }
+}
@@ -1,12 +1,16 @@
+use std::collections::{BTreeMap, HashSet};
+use std::iter::{self, FusedIterator};
+
 use crate::attacks;
 use crate::bitboard::BitBoard;
 use crate::board::Board;
-use crate::castle_rights::CastleMask;
-use crate::color::Color;
+use crate::castle_rights::CastleSide;
 use crate::en_passant::EnPassantSquare;
+use crate::move_list::MoveList;
 use crate::moves::Move;
 use crate::piece::Piece;
 use crate::square::Square;
+use crate::zobrist::Zobrist;
 
 //#################################################################################################
 //
@@ -132,23 +136,10 @@ pub fn gen_castles(board: &Board, mut gen: impl FnMut(Move)) {
     let us = board.get_side_to_move();
     let castle_rights = board.get_castle_rights();
 
-    match us {
-        Color::White => {
-            if castle_rights.has(CastleMask::WhiteOO) & board.is_path_clear(Square::E1, Square::H1) {
-                gen(Move::castle(Square::E1, Square::G1));
-            }
-            if castle_rights.has(CastleMask::WhiteOOO) & board.is_path_clear(Square::E1, Square::A1) {
-                gen(Move::castle(Square::E1, Square::C1));
-            }
-        },
-        Color::Black => {
-            if castle_rights.has(CastleMask::BlackOO) & board.is_path_clear(Square::E8, Square::H8) {
-                gen(Move::castle(Square::E8, Square::G8));
-            }
-            if castle_rights.has(CastleMask::BlackOOO) & board.is_path_clear(Square::E8, Square::A8) {
-                gen(Move::castle(Square::E8, Square::C8));
-            }
-        },
+    for side in CastleSide::SIDES {
+        if castle_rights.can(us, side) && board.is_castle_path_clear(us, side) {
+            gen(board.castle_move(us, side));
+        }
     }
 }
 
@@ -231,26 +222,51 @@ pub fn gen_quiets(board: &Board, mut gen: impl FnMut(Piece, Move)) {
 //
 //#################################################################################################
 
-/// Generates all legal moves for the current position, and pushes them at the end of the buffer, 
-/// in no particular order.
+/// Generates all legal moves for the current position, and pushes them at the end of the buffer,
+/// in the order of gen_legals().
+#[inline]
 pub fn legals(board: &Board, buffer: &mut Vec<Move>) {
-    // Generates all non-king moves with the given consumer.
-    pub fn gen_non_king(board: &Board, mut gen: impl FnMut(Move)) {
-        gen_promote_captures(board, &Piece::PROMOTES, |mv| gen(mv));
-        gen_en_passant(board, |mv| gen(mv));
-        gen_pawn_captures(board, |mv| gen(mv));
-        gen_promotes(board, &Piece::PROMOTES, |mv| gen(mv));
-        gen_pushes(board, |mv| gen(mv));
-        gen_captures(board, |_, mv| gen(mv));
-        gen_quiets(board, |_, mv| gen(mv));
-    }
+    gen_legals(board, |mv| buffer.push(mv));
+}
 
-    let checkers = board.get_checkers();
+/// Generates all legal moves for the current position, and pushes them at the end of the list,
+/// in the order of gen_legals(). Unlike legals(), no heap allocation is ever done.
+#[inline]
+pub fn fill_legals(board: &Board, list: &mut MoveList) {
+    gen_legals(board, |mv| list.push(mv));
+}
 
-    let mut gen = |mv| if board.is_legal(mv) {buffer.push(mv)};
+/// Generates all legal moves for the current position and writes them at the start of the
+/// buffer, in the order of gen_legals(). Returns the number of moves written. Panics if the
+/// buffer is too small: one of move_list::MAX_MOVES moves is always large enough.
+#[inline]
+pub fn legals_into(board: &Board, buffer: &mut [Move]) -> usize {
+    let mut len = 0;
+    gen_legals(board, |mv| {
+        buffer[len] = mv;
+        len += 1;
+    });
+    len
+}
 
-    if checkers.empty() {
+/// Returns the number of legal moves for the current position, without storing them.
+#[inline]
+pub fn count_legals(board: &Board) -> usize {
+    let mut count = 0;
+    gen_legals(board, |_| count += 1);
+    count
+}
+
+/// Gives all legal moves for the current position. The provided closure is called for all
+/// generated moves. The order only depends on the position: castling, king captures and king
+/// quiet moves, then promote captures, en passant, pawn captures, promotions and pushes, then
+/// captures and quiet moves of knights, bishops, rooks and queens, in that order, by increasing
+/// origin then destination square. It is not guaranteed to remain the same between versions:
+/// use legals_map() for an order that will.
+pub fn gen_legals(board: &Board, mut gen: impl FnMut(Move)) {
+    if board.get_checkers().empty() {
         // No checkers.
+        let mut gen = |mv| if board.is_legal(mv) {gen(mv)};
 
         // Generate all castling and king moves. 
         gen_castles(board, |mv| gen(mv));
@@ -259,28 +275,244 @@ pub fn legals(board: &Board, buffer: &mut Vec<Move>) {
 
         // Generates all other moves.
         gen_non_king(board, gen);
-    } else if checkers.is_one() {
-        // One checker.
+    } else {
+        gen_evasions(board, gen);
+    }
+}
 
-        // Generate all king moves.
-        gen_king_captures(board, |mv| gen(mv));
-        gen_king_quiets(board, |mv| gen(mv));
+/// Gives the pseudo-legal moves for the current position that gen_legals() filters, in the same
+/// order: gen_legals() gives those passing Board::is_legal(). In check, only the moves of the king
+/// and those that may capture or block the checker are given. The provided closure is called for
+/// all generated moves.
+pub fn gen_pseudo_legals(board: &Board, mut gen: impl FnMut(Move)) {
+    if board.get_checkers().empty() {
+        gen_castles(board, &mut gen);
+    }
 
-        // Check that the move is either capturing the checker or blocking it.
-        // SAFE: there is always a king on the board.
-        let checker = unsafe {checkers.as_square_unchecked()};
-        let mask = BitBoard::between(board.king_sq(board.get_side_to_move()), checker) | checkers;
-        let gen = |mv: Move| if mask.contains(mv.to()) && board.is_legal(mv) {buffer.push(mv)};
+    gen_king_captures(board, &mut gen);
+    gen_king_quiets(board, &mut gen);
+
+    if let Some(mask) = evasion_mask(board) {
+        gen_non_king(board, |mv| if mask.contains(mv.to()) || mv.is_en_passant() {gen(mv)});
+    }
+}
+
+/// Gives all legal moves for the current position, which must be in check: moves of the king,
+/// captures of the checker and interpositions, in the order of gen_legals(). The provided
+/// closure is called for all generated moves.
+pub fn gen_evasions(board: &Board, mut gen: impl FnMut(Move)) {
+    debug_assert!(board.get_checkers().not_empty(), "gen_evasions() called while not in check");
+
+    // Generate all king moves.
+    gen_legal_king_moves(board, |mv| gen(mv));
 
-        // Generate.
+    // With one checker, the move must either capture the checker or block it. En passant
+    // captures may take the checker without landing on it's square: is_legal() decides.
+    // With two checkers, only the king can move.
+    if let Some(mask) = evasion_mask(board) {
+        let gen = |mv: Move| if (mask.contains(mv.to()) || mv.is_en_passant()) && board.is_legal(mv) {gen(mv)};
         gen_non_king(board, gen);
+    }
+}
+
+/// Gives all legal captures for the current position, en passant and promote captures included.
+/// Along with gen_legal_quiets(), they make up all the legal moves, so that captures may be
+/// searched first without generating the rest. The provided closure is called for all generated moves.
+pub fn gen_legal_captures(board: &Board, mut gen: impl FnMut(Move)) {
+    let mut gen_king = |mv| if board.is_legal(mv) {gen(mv)};
+    gen_king_captures(board, &mut gen_king);
+
+    if let Some(mask) = evasion_mask(board) {
+        let mut gen = |mv: Move| if (mask.contains(mv.to()) || mv.is_en_passant()) && board.is_legal(mv) {gen(mv)};
+
+        gen_promote_captures(board, &Piece::PROMOTES, &mut gen);
+        gen_en_passant(board, &mut gen);
+        gen_pawn_captures(board, &mut gen);
+        gen_captures(board, |_, mv| gen(mv));
+    }
+}
+
+/// Gives all legal moves that capture nothing for the current position: castling, quiet moves,
+/// pushes and promotions. The provided closure is called for all generated moves.
+pub fn gen_legal_quiets(board: &Board, mut gen: impl FnMut(Move)) {
+    let mut gen_king = |mv| if board.is_legal(mv) {gen(mv)};
+
+    if board.get_checkers().empty() {
+        gen_castles(board, &mut gen_king);
+    }
+    gen_king_quiets(board, &mut gen_king);
+
+    if let Some(mask) = evasion_mask(board) {
+        let mut gen = |mv: Move| if mask.contains(mv.to()) && board.is_legal(mv) {gen(mv)};
+
+        gen_promotes(board, &Piece::PROMOTES, &mut gen);
+        gen_pushes(board, &mut gen);
+        gen_quiets(board, |_, mv| gen(mv));
+    }
+}
+
+/// Gives all pseudo-legal moves of pieces other than the king, in the order of gen_legals().
+#[inline]
+fn gen_non_king(board: &Board, mut gen: impl FnMut(Move)) {
+    gen_promote_captures(board, &Piece::PROMOTES, |mv| gen(mv));
+    gen_en_passant(board, |mv| gen(mv));
+    gen_pawn_captures(board, |mv| gen(mv));
+    gen_promotes(board, &Piece::PROMOTES, |mv| gen(mv));
+    gen_pushes(board, |mv| gen(mv));
+    gen_captures(board, |_, mv| gen(mv));
+    gen_quiets(board, |_, mv| gen(mv));
+}
+
+/// Returns the squares pieces other than the king may move to because of checks: all of them if
+/// not in check, those capturing or blocking the checker if in check, and None in double check.
+#[inline]
+fn evasion_mask(board: &Board) -> Option<BitBoard> {
+    let checkers = board.get_checkers();
+
+    if checkers.empty() {
+        Some(BitBoard::FULL)
+    } else if checkers.is_one() {
+        // SAFE: there is exactly one checker.
+        let checker = unsafe {checkers.as_square_unchecked()};
+        Some(BitBoard::between(board.king_sq(board.get_side_to_move()), checker) | checkers)
     } else {
-        // Two checkers.
+        None
+    }
+}
 
-        // Only generate king moves.
-        gen_king_captures(board, |mv| gen(mv));
-        gen_king_quiets(board, |mv| gen(mv));
+/// Returns all legal moves for the current position, keyed by their uci notation, so that they
+/// are iterated in alphabetical order of that notation: stable output for tests and diffs.
+pub fn legals_map(board: &Board) -> BTreeMap<String, Move> {
+    let mut map = BTreeMap::new();
+    gen_legals(board, |mv| {
+        map.insert(mv.to_string(), mv);
+    });
+    map
+}
+
+/// Gives all legal moves of the piece on that square, in no particular order. Gives nothing if
+/// the square is empty or holds a piece of the side not to move. Only the moves of that piece are
+/// generated, which is cheaper than filtering gen_legals() by origin square.
+/// The provided closure is called for all generated moves.
+pub fn gen_legals_from(board: &Board, from: Square, mut gen: impl FnMut(Move)) {
+    let us = board.get_side_to_move();
+    let them = board.get_other_side();
+
+    let piece = match board.get_piece(from) {
+        Some((color, piece)) if color == us => piece,
+        _ => return,
+    };
+
+    // The king escapes checks by itself, is_legal() decides.
+    if piece == Piece::King {
+        let mut gen = |mv| if board.is_legal(mv) {gen(mv)};
+
+        if board.get_checkers().empty() {
+            gen_castles(board, &mut gen);
+        }
+        gen_king_captures(board, &mut gen);
+        gen_king_quiets(board, &mut gen);
+        return;
+    }
+
+    // Other pieces must capture or block the checker, and pins are left to is_legal().
+    let mask = match evasion_mask(board) {
+        Some(mask) => mask,
+        None => return,
+    };
+    let mut gen = |mv: Move| if (mask.contains(mv.to()) || mv.is_en_passant()) && board.is_legal(mv) {gen(mv)};
+
+    let occ = board.get_occupancy().all();
+    let them_occ = board.get_occupancy().colored(them);
+    let free = board.get_occupancy().free();
+
+    let targets = match piece {
+        Piece::Pawn => {
+            let promotes = if BitBoard::promote_rank(us).contains(from) {&Piece::PROMOTES[..]} else {&[]};
+
+            for to in (attacks::pawn(us, from) & them_occ).iter_squares() {
+                let capture = board.get_piece_unchecked(to);
+                if promotes.is_empty() {
+                    gen(Move::capture(from, to, capture));
+                }
+                for &promote in promotes {
+                    gen(Move::promote_capture(from, to, capture, promote));
+                }
+            }
+
+            if let EnPassantSquare::Some(sq) = board.get_ep_square() {
+                let to = attacks::pawn_push(us, sq).unwrap();
+                if attacks::pawn(us, from).contains(to) {
+                    gen(Move::en_passant(from, to));
+                }
+            }
+
+            if let Some(to1) = attacks::pawn_push(us, from) {
+                if free.contains(to1) {
+                    if promotes.is_empty() {
+                        gen(Move::quiet(from, to1));
+                    }
+                    for &promote in promotes {
+                        gen(Move::promote(from, to1, promote));
+                    }
+                    if let Some(to2) = attacks::pawn_double_push(us, from) {
+                        if free.contains(to2) {
+                            gen(Move::double_push(from, to2));
+                        }
+                    }
+                }
+            }
+
+            return;
+        },
+        Piece::Knight => attacks::knight(from),
+        Piece::Bishop => attacks::bishop(from, occ),
+        Piece::Rook => attacks::rook(from, occ),
+        _ => attacks::queen(from, occ),
+    };
+
+    for to in (targets & them_occ).iter_squares() {
+        gen(Move::capture(from, to, board.get_piece_unchecked(to)));
     }
+    for to in (targets & free).iter_squares() {
+        gen(Move::quiet(from, to));
+    }
+}
+
+//#################################################################################################
+//
+//                                    fn legal_king_moves()
+//
+//#################################################################################################
+
+/// Generates all legal moves of the king, castling excluded, and pushes them at the end of
+/// the buffer, in no particular order. Valid in any position, those are the only legal moves
+/// when the king is in double check.
+#[inline]
+pub fn legal_king_moves(board: &Board, buffer: &mut Vec<Move>) {
+    gen_legal_king_moves(board, |mv| buffer.push(mv));
+}
+
+/// Gives all legal moves of the king, castling excluded.
+/// The provided closure is called for all generated moves.
+pub fn gen_legal_king_moves(board: &Board, mut gen: impl FnMut(Move)) {
+    let mut gen = |mv| if board.is_legal(mv) {gen(mv)};
+
+    gen_king_captures(board, |mv| gen(mv));
+    gen_king_quiets(board, |mv| gen(mv));
+}
+
+//#################################################################################################
+//
+//                                   fn gen_checking_moves()
+//
+//#################################################################################################
+
+/// Gives all legal moves that check the opponent, in no particular order.
+/// The provided closure is called for all generated moves.
+#[inline]
+pub fn gen_checking_moves(board: &Board, mut gen: impl FnMut(Move)) {
+    gen_legals(board, |mv| if board.gives_check(mv) {gen(mv)});
 }
 
 //#################################################################################################
@@ -293,24 +525,23 @@ pub fn legals(board: &Board, buffer: &mut Vec<Move>) {
 pub fn perft(board: &mut Board, depth: usize) -> u64 {
     // The real perft function, optimized by bulk counting.
     pub fn internal_perft(board: &mut Board, buffer: &mut Vec<Move>, depth: usize) -> u64 {
+        // Bulk-count the leaves, without storing them.
+        if depth == 1 {
+            return count_legals(board) as u64;
+        }
+
         let start_index = buffer.len();
         legals(board, buffer);
 
-        let total = if depth == 1 {
-            (buffer.len() - start_index) as u64
-        } else {
-            let mut count = 0;
+        let mut total = 0;
 
-            for i in start_index..buffer.len() {
-                let mv = buffer[i];
-
-                board.do_move(mv);
-                count += internal_perft(board, buffer, depth - 1);
-                board.undo_move(mv);
-            }
+        for i in start_index..buffer.len() {
+            let mv = buffer[i];
 
-            count
-        };
+            board.do_move(mv);
+            total += internal_perft(board, buffer, depth - 1);
+            board.undo_move(mv);
+        }
 
         // SAFE: we had at least start_index moves prior to calling this function
         unsafe {buffer.set_len(start_index)};
@@ -324,4 +555,346 @@ pub fn perft(board: &mut Board, depth: usize) -> u64 {
     } else {
         internal_perft(board, &mut Vec::new(), depth)
     }
-}
\ No newline at end of file
+}
+
+//#################################################################################################
+//
+//                                       fn perft_hashed()
+//
+//#################################################################################################
+
+/// The number of entries of the table used by perft_hashed(), 24 MiB worth of them as each
+/// entry takes 24 bytes.
+const PERFT_TABLE_SIZE: usize = 1 << 20;
+
+/// The number of consecutive entries probed by perft_hashed(), starting from the zobrist's index.
+const PERFT_TABLE_PROBES: usize = 4;
+
+/// An entry of the table used by perft_hashed(): the node count of a subtree.
+#[derive(Clone, Copy, Default)]
+struct PerftEntry {
+    zobrist: Zobrist,
+    depth: usize,
+    nodes: u64,
+}
+
+/// Counts the number of leaf nodes of a given position and a given game tree depth, like perft(),
+/// but the node counts of subtrees are stored in a table keyed by zobrist and depth, so that
+/// transpositions are only counted once. Much faster than perft() at high depths, but a zobrist
+/// collision may result in a wrong count.
+pub fn perft_hashed(board: &mut Board, depth: usize) -> u64 {
+    // Returns the node count of that subtree, from the table if possible.
+    fn internal_perft(board: &mut Board, table: &mut [PerftEntry], depth: usize) -> u64 {
+        // Bulk-count the leaves, without storing them.
+        if depth == 1 {
+            return count_legals(board) as u64;
+        }
+
+        let zobrist = board.get_zobrist();
+        let index = zobrist.idx::<PERFT_TABLE_SIZE>() as usize;
+        let bucket = (0..PERFT_TABLE_PROBES).map(|i| (index + i) % PERFT_TABLE_SIZE);
+
+        for i in bucket.clone() {
+            if table[i].zobrist == zobrist && table[i].depth == depth {
+                return table[i].nodes;
+            }
+        }
+
+        let mut nodes = 0;
+        for &mv in MoveList::legals(board).iter() {
+            board.do_move(mv);
+            nodes += internal_perft(board, table, depth - 1);
+            board.undo_move(mv);
+        }
+
+        // Replace the entry with the shallowest subtree, the cheapest to count again.
+        // Empty entries have a depth of 0.
+        let i = bucket.min_by_key(|&i| table[i].depth).unwrap();
+        table[i] = PerftEntry {zobrist, depth, nodes};
+
+        nodes
+    }
+
+    // The internal function will panic if depth is 0.
+    if depth == 0 {
+        1
+    } else {
+        internal_perft(board, &mut vec![PerftEntry::default(); PERFT_TABLE_SIZE], depth)
+    }
+}
+
+//#################################################################################################
+//
+//                                       struct PerftStats
+//
+//#################################################################################################
+
+/// Statistics about the leaf nodes of a game tree, as computed by perft_detailed(). Each leaf is
+/// classified by the move leading to it, so they can be compared with published perft tables.
+#[derive(Clone, Copy, Default, Debug, Eq, PartialEq)]
+pub struct PerftStats {
+    /// The number of leaf nodes.
+    pub nodes: u64,
+    /// The number of captures, en passant included.
+    pub captures: u64,
+    /// The number of en passant captures.
+    pub en_passants: u64,
+    /// The number of castling moves.
+    pub castles: u64,
+    /// The number of promotions, capturing or not.
+    pub promotions: u64,
+    /// The number of moves giving check.
+    pub checks: u64,
+    /// The number of moves giving checkmate.
+    pub checkmates: u64,
+}
+
+/// Counts the leaf nodes of a given position and a given game tree depth, like perft(), also
+/// counting the captures, castles, promotions, checks... leading to them. Much slower than perft()
+/// as leaves can't be bulk-counted: use it to find which kind of moves is miscounted.
+pub fn perft_detailed(board: &mut Board, depth: usize) -> PerftStats {
+    // Recursively expands the tree, classifying the moves leading to the leaves.
+    fn internal_perft(board: &mut Board, depth: usize, stats: &mut PerftStats) {
+        let list = MoveList::legals(board);
+
+        for &mv in list.iter() {
+            board.do_move(mv);
+
+            if depth == 1 {
+                stats.nodes += 1;
+                stats.captures += (mv.is_capture() || mv.is_en_passant()) as u64;
+                stats.en_passants += mv.is_en_passant() as u64;
+                stats.castles += mv.is_castle() as u64;
+                stats.promotions += mv.is_promote() as u64;
+
+                if board.is_check() {
+                    stats.checks += 1;
+                    stats.checkmates += (count_legals(board) == 0) as u64;
+                }
+            } else {
+                internal_perft(board, depth - 1, stats);
+            }
+
+            board.undo_move(mv);
+        }
+    }
+
+    let mut stats = PerftStats::default();
+
+    // The root is the only leaf if depth is 0.
+    if depth == 0 {
+        stats.nodes = 1;
+    } else {
+        internal_perft(board, depth, &mut stats);
+    }
+
+    stats
+}
+
+//#################################################################################################
+//
+//                                       fn enumerate()
+//
+//#################################################################################################
+
+/// Returns an iterator over every position reachable in exactly depth plies from the
+/// given board, found lazily in depth-first order. If dedup is true, positions reached
+/// through different move orders are only yielded once (compared by zobrist keys, so
+/// positions differing only by their en passant square are considered distinct).
+/// The number of positions grows exponentially with the depth (there are already more
+/// than 100 000 000 positions at depth 6 from the starting position), so small depths
+/// are recommended, especially when deduplicating as every key seen has to be kept.
+/// Once exhausted, the iterator keeps returning None.
+pub fn enumerate(board: &Board, depth: usize, dedup: bool) -> impl FusedIterator<Item = Board> {
+    let mut board = board.clone();
+    let mut seen = HashSet::new();
+
+    // For each ply, the legal moves and the index of the next one to try.
+    let mut stack: Vec<(Vec<Move>, usize)> = Vec::new();
+    let mut root = Some(board.clone());
+
+    if depth != 0 {
+        let mut moves = Vec::new();
+        legals(&board, &mut moves);
+        stack.push((moves, 0));
+    }
+
+    iter::from_fn(move || {
+        if depth == 0 {
+            return root.take();
+        }
+
+        loop {
+            let (moves, i) = stack.last_mut()?;
+
+            // All moves were tried at that ply, go back to the previous one.
+            if *i == moves.len() {
+                stack.pop();
+                if let Some((moves, i)) = stack.last() {
+                    board.undo_move(moves[i - 1]);
+                }
+                continue;
+            }
+
+            let mv = moves[*i];
+            *i += 1;
+            board.do_move(mv);
+
+            if stack.len() == depth {
+                let leaf = board.clone();
+                board.undo_move(mv);
+
+                if !dedup || seen.insert(leaf.get_zobrist().get_raw()) {
+                    return Some(leaf);
+                }
+            } else {
+                let mut moves = Vec::new();
+                legals(&board, &mut moves);
+                stack.push((moves, 0));
+            }
+        }
+    }).fuse()
+}
+
+/// The positions of the perft test suite, from https://www.chessprogramming.org/Perft_Results,
+/// and a few more: castles, en passant, promotions and discovered checks all appear within
+/// two plies from them.
+#[cfg(test)]
+pub(crate) const PERFT_FENS: [&str; 6] = [
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3pPk/8/4P3/8 b - g3 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+    "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+    "8/8/8/k2pP2R/8/8/8/4K3 w - d6 0 1",
+];
+
+/// Calls f with every distinct position reachable in two plies from the given fens, and its
+/// legal moves. The fens are parsed by Board::from_fen_960(), which reads standard ones too.
+#[cfg(test)]
+pub(crate) fn for_each_position(fens: &[&str], mut f: impl FnMut(&mut Board, &[Move])) {
+    for fen in fens {
+        for mut board in enumerate(&Board::from_fen_960(fen).unwrap(), 2, true) {
+            let mut moves = Vec::new();
+            legals(&board, &mut moves);
+            f(&mut board, &moves);
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::move_list::MAX_MOVES;
+    use crate::movegen;
+    use crate::moves::Move;
+    use crate::square::Square;
+
+    #[test]
+    fn staged_generation() {
+        crate::init();
+
+        movegen::for_each_position(&movegen::PERFT_FENS, |board, legals| {
+            let mut staged = Vec::new();
+            movegen::gen_legal_captures(board, |mv| {
+                assert!(mv.is_capture() || mv.is_en_passant());
+                staged.push(mv);
+            });
+            movegen::gen_legal_quiets(board, |mv| {
+                assert!(!mv.is_capture() && !mv.is_en_passant());
+                staged.push(mv);
+            });
+
+            assert_eq!(movegen::count_legals(board), legals.len());
+
+            let mut buffer = [Move::quiet(Square::A1, Square::B1); MAX_MOVES];
+            let len = movegen::legals_into(board, &mut buffer);
+            assert_eq!(&buffer[..len], legals);
+
+            if board.is_check() {
+                let mut evasions = Vec::new();
+                movegen::gen_evasions(board, |mv| evasions.push(mv));
+                assert_eq!(evasions, legals, "{}", board);
+            }
+
+            let mut expected = legals.to_vec();
+            expected.sort_by_key(|mv| mv.to_string());
+            staged.sort_by_key(|mv| mv.to_string());
+            assert_eq!(staged, expected, "{}", board);
+        });
+    }
+
+    #[test]
+    fn legals_from() {
+        crate::init();
+
+        movegen::for_each_position(&movegen::PERFT_FENS, |board, legals| {
+            for &sq in Square::SQUARES.iter() {
+                let mut expected: Vec<_> = legals.iter().copied().filter(|mv| mv.from() == sq).collect();
+                let mut moves = board.legal_moves_from(sq);
+
+                expected.sort_by_key(|mv| mv.to_string());
+                moves.sort_by_key(|mv| mv.to_string());
+                assert_eq!(moves, expected, "{} {:?}", board, sq);
+
+                let targets = board.legal_targets_from(sq);
+                assert!(expected.iter().all(|mv| targets.contains(mv.to())));
+                assert_eq!(targets.empty(), expected.is_empty());
+            }
+        });
+    }
+
+    #[test]
+    fn perft_detailed() {
+        crate::init();
+
+        // From https://www.chessprogramming.org/Perft_Results.
+        let stats = movegen::perft_detailed(&mut Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap(), 3);
+        assert_eq!(stats, movegen::PerftStats {
+            nodes: 97862,
+            captures: 17102,
+            en_passants: 45,
+            castles: 3162,
+            promotions: 0,
+            checks: 993,
+            checkmates: 1,
+        });
+
+        let mut board = Board::new("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        let stats = movegen::perft_detailed(&mut board, 4);
+        assert_eq!(stats, movegen::PerftStats {
+            nodes: 43238,
+            captures: 3348,
+            en_passants: 123,
+            castles: 0,
+            promotions: 0,
+            checks: 1680,
+            checkmates: 17,
+        });
+        assert_eq!(stats.nodes, movegen::perft(&mut board, 4));
+    }
+
+    #[test]
+    fn perft_hashed() {
+        crate::init();
+
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+
+        for &fen in fens.iter() {
+            let mut board = Board::new(fen).unwrap();
+            for depth in 0..=4 {
+                assert_eq!(movegen::perft_hashed(&mut board, depth), movegen::perft(&mut board, depth), "{} {}", fen, depth);
+            }
+        }
+    }
+}
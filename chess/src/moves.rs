@@ -1,8 +1,12 @@
+use std::convert::TryFrom;
 use std::fmt;
 use std::num::NonZeroU32;
 use std::sync::atomic::AtomicU32;
 use std::sync::atomic::Ordering;
 
+use anyhow::{Error, Result};
+use serde::{Deserialize, Serialize};
+
 use crate::piece::Piece;
 use crate::prelude::Color;
 use crate::square::Square;
@@ -10,7 +14,7 @@ use crate::square::Square;
 /// Create the base for a move, with the given flags, from and to squares.
 #[inline]
 fn base(flags: u32, from: Square, to: Square) -> NonZeroU32 {
-    // SAFE: from and to are not equal, at least one of them is non zero.
+    // SAFE: from and to are only equal for castles, whose flag is non zero.
     unsafe {NonZeroU32::new_unchecked(flags | (from as u32) << 5 | (to as u32) << 11)}
 }
 
@@ -22,10 +26,12 @@ fn base(flags: u32, from: Square, to: Square) -> NonZeroU32 {
 
 /// A move, encoded in a compact 32 bits representation. 
 /// In big endian, the encoding is done like that:
-/// pppcccttttttffffffmmmmm, where m is the type of the move, 
+/// rrrrrrspppcccttttttffffffmmmmm, where m is the type of the move, 
 /// f is the from square, t is the to square, c is the captured piece
-/// and p is the promote piece.
-#[derive(Clone, Copy, Eq, PartialEq)]
+/// and p is the promote piece. s is set for Chess960 castles, r being
+/// the square of the rook they castle with.
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(into = "u32", try_from = "u32")]
 pub struct Move(NonZeroU32);
 
 // ================================ pub impl
@@ -73,6 +79,12 @@ impl Move {
         Move(base(Move::CASTLE, from, to))
     }
 
+    /// Creates a Chess960 castle move, displayed as the king taking the rook it castles with.
+    #[inline]
+    pub fn castle_960(from: Square, to: Square, rook: Square) -> Move {
+        Move(base(Move::CASTLE, from, to) | 1 << 23 | (rook as u32) << 24)
+    }
+
     /// Returns true if the move is quiet.
     #[inline]
     pub fn is_quiet(self) -> bool {
@@ -159,11 +171,36 @@ impl Move {
 
 // ================================ traits impl
 
+impl From<Move> for u32 {
+    /// Returns the raw value of the move, as Move::get_raw().
+    fn from(mv: Move) -> u32 {
+        mv.get_raw()
+    }
+}
+
+impl TryFrom<u32> for Move {
+    type Error = Error;
+
+    /// Tries to get a move back from its raw value. The pieces and the unused bits are
+    /// checked, the legality of the move is not.
+    fn try_from(raw: u32) -> Result<Move> {
+        let valid = raw >> 30 == 0 && (raw >> 17 & 0x7) < 6 && (raw >> 20 & 0x7) < 6;
+
+        match NonZeroU32::new(raw) {
+            Some(raw) if valid => Ok(Move(raw)),
+            _ => Err(Error::msg("Invalid raw move.")),
+        }
+    }
+}
+
 impl fmt::Display for Move {
     /// Displays a move using pure algebraic coordinate notation.
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         if self.is_promote() {
             write!(fmt, "{}{}{}", self.from(), self.to(), self.get_promote().as_char(Color::Black))
+        } else if self.is_castle() && u32::from(self.0) >> 23 & 1 != 0 {
+            // In Chess960, the king takes it's own rook.
+            write!(fmt, "{}{}", self.from(), Square::SQUARES[(u32::from(self.0) >> 24 & 0x3F) as usize])
         } else {
             write!(fmt, "{}{}", self.from(), self.to())
         }
@@ -0,0 +1,124 @@
+use crate::bitboard::BitBoard;
+use crate::color::Color;
+use crate::square::Square;
+
+//#################################################################################################
+//
+//                                            tables
+//
+//#################################################################################################
+
+// These arrays contain bitboards indexed by a color and a square. They contain respectively:
+// - the squares in front of a pawn of that color on that square, on the same file.
+// - the squares a pawn of that color on that square could attack as it advances.
+static mut FRONT_SPANS: [[BitBoard; 64]; 2] = [[BitBoard::EMPTY; 64]; 2];
+static mut ATTACK_SPANS: [[BitBoard; 64]; 2] = [[BitBoard::EMPTY; 64]; 2];
+
+/// Initializes the span tables.
+#[cold]
+pub(crate) unsafe fn init() {
+    for color in Color::COLORS {
+        let dy = match color {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+
+        for sq in Square::SQUARES {
+            let i = usize::from(color);
+            let j = usize::from(sq);
+
+            let mut cur = sq;
+            while let Some(next) = cur.displace((0, dy)) {
+                FRONT_SPANS[i][j] |= next.into();
+
+                for dx in [-1, 1] {
+                    if let Some(attacked) = next.displace((dx, 0)) {
+                        ATTACK_SPANS[i][j] |= attacked.into();
+                    }
+                }
+
+                cur = next;
+            }
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                          functions
+//
+//#################################################################################################
+
+/// Returns the squares in front of a pawn of that color on that square, on the same file.
+#[inline]
+pub fn front_span(color: Color, sq: Square) -> BitBoard {
+    // SAFE: array is initialized at startup
+    unsafe {FRONT_SPANS[usize::from(color)][usize::from(sq)]}
+}
+
+/// Returns the squares a pawn of that color on that square could attack as it advances,
+/// that is the squares in front of it on the adjacent files.
+#[inline]
+pub fn attack_span(color: Color, sq: Square) -> BitBoard {
+    // SAFE: array is initialized at startup
+    unsafe {ATTACK_SPANS[usize::from(color)][usize::from(sq)]}
+}
+
+/// Returns the pawns of that color, along with all the squares in front of them.
+#[inline]
+pub fn front_fill(color: Color, mut pawns: BitBoard) -> BitBoard {
+    match color {
+        Color::White => {
+            pawns |= pawns << 8;
+            pawns |= pawns << 16;
+            pawns | pawns << 32
+        },
+        Color::Black => {
+            pawns |= pawns >> 8;
+            pawns |= pawns >> 16;
+            pawns | pawns >> 32
+        },
+    }
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use crate::bitboard::BitBoard;
+    use crate::color::Color;
+    use crate::square::Square;
+
+    #[test]
+    fn spans() {
+        crate::init();
+
+        assert_eq!(super::front_span(Color::White, Square::E2), BitBoard::FILE_E & !(BitBoard::RANK_1 | BitBoard::RANK_2));
+        assert_eq!(super::front_span(Color::Black, Square::E2), BitBoard::FILE_E & BitBoard::RANK_1);
+        assert_eq!(super::front_span(Color::White, Square::H8), BitBoard::EMPTY);
+
+        // On the edges, only one adjacent file.
+        assert_eq!(super::attack_span(Color::White, Square::A6), BitBoard::FILE_B & (BitBoard::RANK_7 | BitBoard::RANK_8));
+        assert_eq!(super::attack_span(Color::Black, Square::H3), BitBoard::FILE_G & (BitBoard::RANK_1 | BitBoard::RANK_2));
+        assert_eq!(super::attack_span(Color::White, Square::D7), (BitBoard::FILE_C | BitBoard::FILE_E) & BitBoard::RANK_8);
+    }
+
+    #[test]
+    fn front_fill() {
+        crate::init();
+
+        let pawns = BitBoard::from(Square::B2) | BitBoard::from(Square::G5);
+        assert_eq!(
+            super::front_fill(Color::White, pawns),
+            BitBoard::FILE_B & !BitBoard::RANK_1 | BitBoard::FILE_G & (BitBoard::RANK_5 | BitBoard::RANK_6 | BitBoard::RANK_7 | BitBoard::RANK_8),
+        );
+        assert_eq!(
+            super::front_fill(Color::Black, pawns),
+            BitBoard::FILE_B & (BitBoard::RANK_1 | BitBoard::RANK_2) | BitBoard::FILE_G & !(BitBoard::RANK_6 | BitBoard::RANK_7 | BitBoard::RANK_8),
+        );
+    }
+}
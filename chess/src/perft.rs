@@ -1,6 +1,7 @@
 use std::env;
 use std::str::FromStr;
 use std::thread;
+use std::time::Instant;
 
 use anyhow::{Error, Result};
 use clap::App;
@@ -22,6 +23,14 @@ use clap::Arg;
  * For a quick and dirty benchmark:
  * $ cargo build --bin perft --release 
  * $ time target/release/perft 6 "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+ *
+ * To benchmark the move generator alone (the timing excludes initialization and parsing):
+ * $ cargo build --bin perft --release 
+ * $ target/release/perft --nps 6 "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+ *
+ * To compare the number of captures, castles, checks... with published perft tables:
+ * $ cargo build --bin perft --release 
+ * $ target/release/perft --stats 5 "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
  */
 
 fn main() -> Result<()> {
@@ -44,6 +53,12 @@ fn main() -> Result<()> {
             .index(3)
             .value_name("MOVES")
             .help("A space seperated serie of moves to perform before beginning game tree expansion."))
+        .arg(Arg::with_name("nps")
+            .long("nps")
+            .help("Also prints the time taken and the number of nodes per second, after the total."))
+        .arg(Arg::with_name("stats")
+            .long("stats")
+            .help("Also prints the number of captures, en passants, castles, promotions, checks and checkmates at the leaves, after the total."))
         .get_matches();
 
     // Parse depth.
@@ -66,9 +81,11 @@ fn main() -> Result<()> {
         }
     }
 
-    // Compute the legal moves of the starting position.
-    let mut list = Vec::new();
-    movegen::legals(&board, &mut list);
+    // Start timing once everything is parsed.
+    let start = Instant::now();
+
+    // Compute the legal moves of the starting position, in alphabetical order.
+    let list: Vec<_> = movegen::legals_map(&board).into_values().collect();
 
     // The total number of nodes.
     let mut total = 0;
@@ -106,6 +123,22 @@ fn main() -> Result<()> {
     // Print the total after an empty line.
     println!("\n{}", total);
 
+    // Print the timing if asked to, after the total so as not to confuse perftree.
+    if args.is_present("nps") {
+        let elapsed = start.elapsed();
+        let nps = (total as f64 / elapsed.as_secs_f64().max(1e-9)) as u64;
+        println!("{} nodes in {:.3}s: {} nps", total, elapsed.as_secs_f64(), nps);
+    }
+
+    // Print the leaf statistics if asked to, computed separately as it is much slower.
+    if args.is_present("stats") {
+        let stats = movegen::perft_detailed(&mut board, depth);
+        println!(
+            "captures: {}, en passants: {}, castles: {}, promotions: {}, checks: {}, checkmates: {}",
+            stats.captures, stats.en_passants, stats.castles, stats.promotions, stats.checks, stats.checkmates,
+        );
+    }
+
     // Successfully return.
     Ok(())
 }
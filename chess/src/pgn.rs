@@ -0,0 +1,431 @@
+use anyhow::{Error, Result};
+
+use crate::adjudicator::GameResult;
+use crate::board::{Board, STARTPOS_FEN};
+use crate::color::Color;
+use crate::moves::Move;
+
+/// The maximum length of the lines of the movetext, as advised by the pgn standard.
+const MAX_LINE_LEN: usize = 79;
+
+/// Returns the result of the game given by that result token of the pgn standard, None for
+/// "*" if the game is not over, or an error if it is not a result token.
+fn parse_result(token: &str) -> Result<Option<GameResult>> {
+    match token {
+        "1-0" => Ok(Some(GameResult::Win(Color::White))),
+        "0-1" => Ok(Some(GameResult::Win(Color::Black))),
+        "1/2-1/2" => Ok(Some(GameResult::Draw)),
+        "*" => Ok(None),
+        _ => Err(Error::msg(format!("Invalid result token: {:?}.", token))),
+    }
+}
+
+/// Returns the result token of the pgn standard: "1-0", "0-1", "1/2-1/2", or "*" if the game is not over.
+fn result_token(result: Option<GameResult>) -> &'static str {
+    match result {
+        Some(GameResult::Win(Color::White)) => "1-0",
+        Some(GameResult::Win(Color::Black)) => "0-1",
+        Some(GameResult::Draw) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+//#################################################################################################
+//
+//                                        struct PgnWriter
+//
+//#################################################################################################
+
+/// Exports played games to pgn, with the seven tags roster and the moves in standard
+/// algebraic notation. The tags are the same for all games written, and default to "?",
+/// as the pgn standard wants for unknown values.
+#[derive(Clone, Debug)]
+pub struct PgnWriter {
+    /// The name of the tournament or match.
+    pub event: String,
+    /// The location of the event.
+    pub site: String,
+    /// The starting date of the game, as "YYYY.MM.DD".
+    pub date: String,
+    /// The round of the game in the event.
+    pub round: String,
+    /// The player of the white pieces.
+    pub white: String,
+    /// The player of the black pieces.
+    pub black: String,
+}
+
+// ================================ pub impl
+
+impl PgnWriter {
+    /// Writes the game played from the given fen, the moves being legal in turn, and ending
+    /// with the given result, None meaning the game is not over. A "SetUp" and a "FEN" tag are
+    /// added when the game does not start from the starting position. Returns an error if the
+    /// fen or one of the moves is invalid.
+    pub fn write(&self, fen: &str, moves: &[Move], result: Option<GameResult>) -> Result<String> {
+        let mut board = Board::new(fen)?;
+        let result = result_token(result);
+
+        // The seven tag roster, followed by the setup if any.
+        let mut pgn = String::new();
+        for (name, value) in [
+            ("Event", &*self.event),
+            ("Site", &self.site),
+            ("Date", &self.date),
+            ("Round", &self.round),
+            ("White", &self.white),
+            ("Black", &self.black),
+            ("Result", result),
+        ] {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+        }
+
+        let fen = board.to_string();
+        if fen != STARTPOS_FEN {
+            pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen));
+        }
+        pgn.push('\n');
+
+        // The movetext, numbering white's moves, and black's first one if it starts.
+        let mut tokens = Vec::with_capacity(moves.len() * 3 / 2 + 1);
+        for (i, &mv) in moves.iter().enumerate() {
+            if !board.is_pseudo_legal(mv) || !board.is_legal(mv) {
+                return Err(Error::msg(format!("Illegal move #{}: {}.", i, mv)));
+            }
+
+            let fullmove = board.get_ply() / 2 + 1;
+            match board.get_side_to_move() {
+                Color::White => tokens.push(format!("{}.", fullmove)),
+                Color::Black if i == 0 => tokens.push(format!("{}...", fullmove)),
+                Color::Black => (),
+            }
+
+            tokens.push(board.move_to_san(mv));
+            board.do_move(mv);
+        }
+        tokens.push(String::from(result));
+
+        // Wrap the movetext.
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len != 0 && line_len + 1 + token.len() > MAX_LINE_LEN {
+                pgn.push('\n');
+                line_len = 0;
+            } else if line_len != 0 {
+                pgn.push(' ');
+                line_len += 1;
+            }
+
+            line_len += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+
+        Ok(pgn)
+    }
+}
+
+// ================================ traits impl
+
+impl Default for PgnWriter {
+    /// All the tags are unknown.
+    fn default() -> PgnWriter {
+        PgnWriter {
+            event: String::from("?"),
+            site: String::from("?"),
+            date: String::from("????.??.??"),
+            round: String::from("?"),
+            white: String::from("?"),
+            black: String::from("?"),
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                        struct PgnReader
+//
+//#################################################################################################
+
+/// Imports a single game from pgn: its tags, and the moves of its movetext, in standard algebraic
+/// notation, played in turn from the position of the "FEN" tag or from the starting position.
+/// Comments, variations, numeric annotation glyphs and move numbers are skipped.
+#[derive(Clone, Debug)]
+pub struct PgnReader {
+    tags: Vec<(String, String)>,
+    board: Board,
+    moves: Vec<Move>,
+    result: Option<GameResult>,
+}
+
+// ================================ pub impl
+
+impl PgnReader {
+    /// Parses the game, up to the result token or the end of the pgn. Returns an error if a tag
+    /// or the fen is invalid, if a comment or variation is not closed, or if a move is illegal.
+    pub fn parse(pgn: &str) -> Result<PgnReader> {
+        let mut tags = Vec::new();
+        let mut lines = pgn.lines().map(str::trim).peekable();
+
+        // The tag pairs, [Name "value"], come first.
+        while let Some(line) = lines.next_if(|line| line.is_empty() || line.starts_with('[')) {
+            if !line.is_empty() {
+                tags.push(PgnReader::parse_tag(line)?);
+            }
+        }
+
+        let fen = tags.iter().find(|(name, _)| name == "FEN").map_or(STARTPOS_FEN, |(_, fen)| fen);
+        let mut board = Board::new(fen)?;
+        let start = board.clone();
+
+        // The movetext, without the escaped lines.
+        let movetext = lines.filter(|line| !line.starts_with('%')).collect::<Vec<_>>().join("\n");
+
+        let mut moves = Vec::new();
+        let mut result = None;
+
+        for token in PgnReader::tokens(&movetext)? {
+            if let Ok(res) = parse_result(&token) {
+                result = res;
+                break;
+            }
+
+            // Move numbers may be followed by the move without a space: "12.e4", "12...e5".
+            let san = token.rsplit('.').next().unwrap_or_default();
+            if san.is_empty() || san.starts_with('$') || san.chars().all(|c| c.is_ascii_digit() || "!?".contains(c)) {
+                continue;
+            }
+
+            let mv = board.parse_san(san).map_err(|e| Error::msg(format!("Move #{}: {}", moves.len(), e)))?;
+            board.do_move(mv);
+            moves.push(mv);
+        }
+
+        Ok(PgnReader {
+            tags,
+            board: start,
+            moves,
+            result,
+        })
+    }
+
+    /// Parses all the games of a pgn file, each one starting at its first tag pair.
+    /// Returns an error naming the game if one of them can't be parsed.
+    pub fn parse_games(pgn: &str) -> Result<Vec<PgnReader>> {
+        let mut games = Vec::new();
+        let mut game = String::new();
+        let mut in_movetext = false;
+
+        for line in pgn.lines() {
+            let is_tag = line.trim_start().starts_with('[');
+
+            // A tag pair after the movetext starts the next game.
+            if is_tag && in_movetext {
+                games.push(std::mem::take(&mut game));
+                in_movetext = false;
+            }
+
+            in_movetext |= !is_tag && !line.trim().is_empty();
+            game.push_str(line);
+            game.push('\n');
+        }
+
+        if in_movetext {
+            games.push(game);
+        }
+
+        games.iter().enumerate()
+            .map(|(i, game)| PgnReader::parse(game).map_err(|e| Error::msg(format!("Game #{}: {}", i, e))))
+            .collect()
+    }
+
+    /// Returns the value of the tag of that name, if any.
+    #[inline]
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the tag pairs of the game, in order.
+    #[inline]
+    pub fn get_tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Returns the starting position of the game.
+    #[inline]
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns the moves of the game, legal in turn from the starting position.
+    #[inline]
+    pub fn get_moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Returns the result of the game, None if it is not over or has no result token.
+    #[inline]
+    pub fn get_result(&self) -> Option<GameResult> {
+        self.result
+    }
+}
+
+// ================================ impl
+
+impl PgnReader {
+    /// Parses a tag pair, [Name "value"], unescaping the value.
+    fn parse_tag(line: &str) -> Result<(String, String)> {
+        let invalid = || Error::msg(format!("Invalid tag pair: {:?}.", line));
+
+        let inner = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')).ok_or_else(invalid)?;
+        let (name, value) = inner.split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let value = value.trim().strip_prefix('"').and_then(|value| value.strip_suffix('"')).ok_or_else(invalid)?;
+
+        let mut unescaped = String::with_capacity(value.len());
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            unescaped.push(if c == '\\' {chars.next().ok_or_else(invalid)?} else {c});
+        }
+
+        Ok((name.to_string(), unescaped))
+    }
+
+    /// Splits the movetext into tokens, without the comments, {...} or up to the end of the
+    /// line after a ';', and without the variations, (...), which may be nested.
+    fn tokens(movetext: &str) -> Result<Vec<String>> {
+        let mut tokens = Vec::new();
+        let mut token = String::new();
+        let mut chars = movetext.chars();
+        let mut variations = 0;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => if !chars.any(|c| c == '}') {
+                    return Err(Error::msg("Unclosed comment in the movetext."));
+                },
+                ';' => drop(chars.any(|c| c == '\n')),
+                '(' => variations += 1,
+                ')' if variations > 0 => variations -= 1,
+                ')' => return Err(Error::msg("Unopened variation in the movetext.")),
+                _ if variations > 0 => (),
+                c if c.is_whitespace() => (),
+                c => {
+                    token.push(c);
+                    continue;
+                },
+            }
+
+            if !token.is_empty() {
+                tokens.push(std::mem::take(&mut token));
+            }
+        }
+
+        if variations > 0 {
+            return Err(Error::msg("Unclosed variation in the movetext."));
+        }
+        if !token.is_empty() {
+            tokens.push(token);
+        }
+
+        Ok(tokens)
+    }
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use crate::adjudicator::GameResult;
+    use crate::board::Board;
+    use crate::color::Color;
+    use crate::moves::Move;
+    use super::{PgnReader, PgnWriter};
+
+    /// Parses the moves in turn from the fen, in uci notation.
+    fn parse_moves(fen: &str, moves: &[&str]) -> Vec<Move> {
+        let mut board = Board::new(fen).unwrap();
+        moves.iter().map(|s| {
+            let mv = board.parse_move(s).unwrap();
+            board.do_move(mv);
+            mv
+        }).collect()
+    }
+
+    #[test]
+    fn write() {
+        crate::init();
+
+        let writer = PgnWriter {
+            white: String::from("Rush"),
+            black: String::from("Rush \"2\""),
+            ..PgnWriter::default()
+        };
+
+        // Castling and check, wrapping the movetext.
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let moves = parse_moves(fen, &[
+            "e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "f8c5", "e1g1", "g8f6", "d2d3", "e8g8", "c1g5", "h7h6",
+            "g5h4", "g7g5", "h4g3", "d7d6", "c2c3", "c8g4", "b1d2", "d8e7", "c4f7", "f8f7",
+        ]);
+        assert_eq!(writer.write(fen, &moves, Some(GameResult::Draw)).unwrap(), concat!(
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n",
+            "[White \"Rush\"]\n[Black \"Rush \\\"2\\\"\"]\n[Result \"1/2-1/2\"]\n\n",
+            "1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O Nf6 5. d3 O-O 6. Bg5 h6 7. Bh4 g5 8. Bg3\n",
+            "d6 9. c3 Bg4 10. Nbd2 Qe7 11. Bxf7+ Rxf7 1/2-1/2\n",
+        ));
+
+        // Checkmate.
+        let moves = parse_moves(fen, &["e2e4", "e7e5", "f1c4", "b8c6", "d1h5", "g8f6", "h5f7"]);
+        assert!(writer.write(fen, &moves, Some(GameResult::Win(Color::White))).unwrap().ends_with("\n\n1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n"));
+
+        // Black to move first, with a queenside castle and an underpromotion with check.
+        let fen = "r3k3/8/8/8/2K5/8/1p6/8 b q - 0 40";
+        let moves = parse_moves(fen, &["e8c8", "c4c3", "b2b1n"]);
+        assert!(writer.write(fen, &moves, None).unwrap().ends_with(concat!(
+            "[Result \"*\"]\n[SetUp \"1\"]\n[FEN \"r3k3/8/8/8/2K5/8/1p6/8 b q - 0 40\"]\n\n",
+            "40... O-O-O 41. Kc3 b1=N+ *\n",
+        )));
+
+        assert!(writer.write(fen, &moves[1..], None).is_err());
+    }
+
+    #[test]
+    fn read() {
+        crate::init();
+
+        // Comments, nested variations, annotation glyphs and glued move numbers are skipped.
+        let pgn = concat!(
+            "[Event \"Casual \\\"blitz\\\"\"]\n",
+            "[Result \"1-0\"]\n",
+            "\n",
+            "1. e4 {best by test} e5 2.Nf3 (2. Bc4 Nc6 (2... Nf6) 3. Qh5) 2... Nc6 $1 3. Bc4 Nf6?!\n",
+            "; a rest of line comment (\n",
+            "4. O-O Nxe4 5. Re1 1-0 6. Rxe4\n",
+        );
+        let game = PgnReader::parse(pgn).unwrap();
+
+        assert_eq!(game.get_tag("Event"), Some("Casual \"blitz\""));
+        assert_eq!(game.get_tag("Site"), None);
+        assert_eq!(game.get_result(), Some(GameResult::Win(Color::White)));
+
+        let moves = game.get_moves().iter().map(|mv| mv.to_string()).collect::<Vec<_>>();
+        assert_eq!(moves, ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1g1", "f6e4", "f1e1"]);
+
+        // From the position of the fen tag, round-tripping through the writer.
+        let fen = "r3k3/8/8/8/2K5/8/1p6/8 b q - 0 40";
+        let moves = parse_moves(fen, &["e8c8", "c4c3", "b2b1n"]);
+        let game = PgnReader::parse(&PgnWriter::default().write(fen, &moves, None).unwrap()).unwrap();
+
+        assert_eq!(game.get_board().to_string(), fen);
+        assert_eq!(game.get_moves(), &moves[..]);
+        assert_eq!(game.get_result(), None);
+
+        assert!(PgnReader::parse("1. e4 e5 2. Ke3").is_err());
+        assert!(PgnReader::parse("1. e4 {e5").is_err());
+        assert!(PgnReader::parse("1. e4 (1. d4").is_err());
+    }
+}
@@ -34,6 +34,32 @@ impl Piece {
         Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight,
     ];
 
+    /// The weight of each piece in the game phase: the total weight of the pieces
+    /// of the starting position is Piece::MAX_PHASE.
+    const PHASE_WEIGHTS: [u8; 6] = [0, 1, 1, 2, 4, 0];
+
+    /// The game phase of the starting position, see Piece::phase_weight().
+    pub const MAX_PHASE: u8 = 24;
+
+    /// Returns the material value of the piece in the middlegame, in centipawns.
+    #[inline]
+    pub const fn mg_value(self) -> i32 {
+        [82, 337, 365, 477, 1025, 0][self as usize]
+    }
+
+    /// Returns the material value of the piece in the endgame, in centipawns.
+    #[inline]
+    pub const fn eg_value(self) -> i32 {
+        [94, 281, 297, 512, 936, 0][self as usize]
+    }
+
+    /// Returns the weight of the piece in the game phase: 0 for pawns and kings,
+    /// 1 for minor pieces, 2 for rooks and 4 for queens.
+    #[inline]
+    pub const fn phase_weight(self) -> u8 {
+        Piece::PHASE_WEIGHTS[self as usize]
+    }
+
     /// Tries to parse a piece from a single char.
     pub fn from_char(c: char) -> Result<(Color, Piece), Error> {
         match c {
@@ -79,6 +79,12 @@ impl Square {
         }
     }
 
+    /// Returns true if both squares have the same color on the board.
+    #[inline]
+    pub fn same_color(self, other: Square) -> bool {
+        (self.x() + self.y()) % 2 == (other.x() + other.y()) % 2
+    }
+
     /// Displaces the square by dx, dy, return None if the square is off the board.
     pub fn displace(self, (dx, dy): (i8, i8)) -> Option<Square> {
         let x = self.x() as i8 + dx;
@@ -166,4 +172,29 @@ impl FromStr for Square {
             Err(Error::msg("a square should be exactly 2 characters long"))
         }
     }
-}
\ No newline at end of file
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+    use crate::square::Square;
+
+    #[test]
+    fn colors() {
+        assert_eq!(Square::A1.parity(), Color::Black);
+        assert_eq!(Square::H1.parity(), Color::White);
+        assert_eq!(Square::D1.parity(), Color::White);
+        assert_eq!(Square::D8.parity(), Color::Black);
+
+        assert!(Square::C1.same_color(Square::H6));
+        assert!(Square::F1.same_color(Square::D1));
+        assert!(!Square::C1.same_color(Square::F1));
+        assert!(!Square::A1.same_color(Square::A2));
+    }
+}
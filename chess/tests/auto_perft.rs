@@ -139,4 +139,9 @@ fn auto_perft() {
         let mut board = Board::new(fen).unwrap();
         assert_eq!(movegen::perft(&mut board, 4), res, "Error at {:?}.", fen);
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn self_test() {
+    assert_eq!(chess::self_test(), Ok(()));
+}
@@ -6,12 +6,10 @@ use std::str::FromStr;
 use anyhow::{Error, Result};
 use clap::{App, Arg};
 
+use chess::board::STARTPOS_FEN;
 use chess::prelude::*;
 use engine::Engine;
 
-/// The default fen used, the starting position.
-const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
-
 /// The maximum number of moves displayed in move history.
 const MAX_HISTORY: usize = 24;
 
@@ -194,6 +192,9 @@ impl State {
 
     /// Resets the board to it's initial state.
     fn reset(&mut self, fen: &str) -> Result<()> {
+        // Forget about the previous game.
+        self.engine.clear_table();
+
         // Reset the board.
         let mut board = self.engine.write_board();
         *board = Board::new(fen).unwrap();
@@ -225,7 +226,7 @@ fn main() -> Result<()> {
             .short("f")
             .long("fen")
             .value_name("FEN")
-            .default_value(DEFAULT_FEN)
+            .default_value(STARTPOS_FEN)
             .help("Sets the fen string to use as the starting position, use double quotes to give everything in a single argument.")
             .takes_value(true))
         .arg(Arg::with_name("book")
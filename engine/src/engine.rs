@@ -1,7 +1,7 @@
 use std::fmt;
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
-use std::sync::{Arc, Barrier, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Barrier, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
@@ -12,9 +12,10 @@ use chess::book::Book;
 use chess::moves::{AtomicMove, Move};
 
 use crate::eval::Net;
-use crate::{params, utils};
-use crate::search::Search;
+use crate::{params, uci, utils};
+use crate::search::{self, Search};
 use crate::table::TranspositionTable;
+use crate::time::TimeManager;
 
 //#################################################################################################
 //
@@ -34,12 +35,73 @@ pub(crate) struct GlobalInfo {
     search_id: AtomicU8,
     best_move: AtomicMove,
 
+    nodes: AtomicU64,
+    qnodes: AtomicU64,
+    tt_hits: AtomicU64,
+    start: Mutex<utils::Clock>,
+    reporter: Reporter,
+
+    time: RwLock<Option<TimeManager>>,
+    time_up: AtomicBool,
+
+    contempt: AtomicI32,
+    root_moves: RwLock<Vec<Move>>,
     board: RwLock<Board>,
 }
 
 // ================================ pub(crate) impl
 
 impl GlobalInfo {
+    /// Creates the shared info for the given number of threads waiting at the barrier.
+    pub(crate) fn new(board: Board, num_threads: usize, table: TranspositionTable) -> GlobalInfo {
+        GlobalInfo {
+            barrier: Barrier::new(num_threads),
+            searching: AtomicBool::new(false),
+            stop: AtomicBool::new(false),
+            
+            table,
+            search_depth: AtomicU8::new(0),
+            search_id: AtomicU8::new(0),
+            best_move: AtomicMove::default(),
+
+            nodes: AtomicU64::new(0),
+            qnodes: AtomicU64::new(0),
+            tt_hits: AtomicU64::new(0),
+            start: Mutex::new(utils::Clock::start()),
+            reporter: Reporter::default(),
+
+            time: RwLock::new(None),
+            time_up: AtomicBool::new(false),
+
+            contempt: AtomicI32::new(0),
+            root_moves: RwLock::new(Vec::new()),
+            board: RwLock::new(board),
+        }
+    }
+
+    /// Creates the shared info of a search that is not driven by the engine's thread pool,
+    /// already flagged as searching. The board, the contempt and the root moves are copied
+    /// from this one, and the search gets its own fresh table of the same size.
+    pub(crate) fn standalone(&self) -> GlobalInfo {
+        let info = GlobalInfo::new(self.board(), 1, TranspositionTable::with_size_mb(self.table.size_mb()));
+        info.searching.store(true, Ordering::Release);
+        info.contempt.store(self.contempt.load(Ordering::Relaxed), Ordering::Relaxed);
+        *info.root_moves.write().unwrap() = self.root_moves();
+        info
+    }
+
+    /// Returns the contempt, in pawns: how much the engine's side dislikes draws.
+    #[inline]
+    pub(crate) fn contempt(&self) -> f32 {
+        self.contempt.load(Ordering::Relaxed) as f32 / 100.0
+    }
+
+    /// Returns the moves the search is restricted to at the root, all moves if empty.
+    #[inline]
+    pub(crate) fn root_moves(&self) -> Vec<Move> {
+        self.root_moves.read().unwrap().clone()
+    }
+
     /// Returns a reference to the TranspositionTable.
     #[inline]
     pub(crate) fn get_table(&self) -> &TranspositionTable {
@@ -58,6 +120,24 @@ impl GlobalInfo {
         self.searching.load(Ordering::Relaxed)
     }
 
+    /// Returns the time manager of the search, if it is timed.
+    #[inline]
+    pub(crate) fn time_manager(&self) -> Option<TimeManager> {
+        *self.time.read().unwrap()
+    }
+
+    /// Returns true if the time of the search is up, and the threads must stop searching.
+    #[inline]
+    pub(crate) fn is_time_up(&self) -> bool {
+        self.time_up.load(Ordering::Relaxed)
+    }
+
+    /// Tells every thread that the time of the search is up.
+    #[inline]
+    pub(crate) fn set_time_up(&self) {
+        self.time_up.store(true, Ordering::Relaxed);
+    }
+
     /// Atomically looks for the stop signal.
     #[inline]
     pub(crate) fn should_stop(&self) -> bool {
@@ -97,22 +177,47 @@ impl GlobalInfo {
         1 + depth + (id + 1).trailing_zeros() as u8 
     }
 
+    /// Adds the counters of the given statistics to the totals of the search.
+    #[inline]
+    pub(crate) fn add_stats(&self, stats: &SearchStats) {
+        self.nodes.fetch_add(stats.nodes, Ordering::Relaxed);
+        self.qnodes.fetch_add(stats.qnodes, Ordering::Relaxed);
+        self.tt_hits.fetch_add(stats.tt_hits, Ordering::Relaxed);
+    }
+
+    /// Returns the statistics of the search since it started.
+    #[inline]
+    pub(crate) fn stats(&self) -> SearchStats {
+        SearchStats {
+            nodes: self.nodes.load(Ordering::Relaxed),
+            qnodes: self.qnodes.load(Ordering::Relaxed),
+            tt_hits: self.tt_hits.load(Ordering::Relaxed),
+            elapsed: self.start.lock().unwrap().elapsed(),
+        }
+    }
+
     /// Report back a move, stores if it was searched at a deeper depth
     /// than the current one, and subsequently increase the base search depth.
+    /// Then forwards the informations of that iteration to the reporter.
     #[inline]
-    pub(crate) fn report_move(&self, mv: Move, depth: u8) {
-        self.search_depth.fetch_update(
+    pub(crate) fn report_move(&self, mv: Move, score: f32, depth: u8) {
+        let res = self.search_depth.fetch_update(
             Ordering::SeqCst,
             Ordering::SeqCst,
-            |cur_depth| {
-                if depth <= cur_depth {
-                    None
-                } else {
-                    self.best_move.store(mv);
-                    Some(depth)
-                }
-            }
-        ).ok();
+            |cur_depth| if depth <= cur_depth {None} else {Some(depth)},
+        );
+
+        if res.is_ok() {
+            self.best_move.store(mv);
+
+            self.reporter.report(&IterationInfo {
+                depth,
+                score,
+                stats: self.stats(),
+                pv: self.table.principal_variation(&self.board(), mv),
+                hashfull: self.table.hashfull(),
+            });
+        }
     }
 }
 
@@ -179,6 +284,106 @@ impl fmt::Display for EngineStatus {
     }
 }
 
+//#################################################################################################
+//
+//                                      struct SearchStats
+//
+//#################################################################################################
+
+/// The statistics of a search, telling how much work it did.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchStats {
+    /// The number of nodes searched, including the nodes of the quiescence search.
+    pub nodes: u64,
+    /// The number of nodes searched by the quiescence search.
+    pub qnodes: u64,
+    /// The number of nodes whose score was found in the transposition table.
+    pub tt_hits: u64,
+    /// The time elapsed since the search started.
+    pub elapsed: Duration,
+}
+
+// ================================ pub impl
+
+impl SearchStats {
+    /// The number of nodes searched per second.
+    pub fn nps(&self) -> u64 {
+        let micros = self.elapsed.as_micros().max(1) as u64;
+        self.nodes.saturating_mul(1_000_000) / micros
+    }
+}
+
+//#################################################################################################
+//
+//                                     struct IterationInfo
+//
+//#################################################################################################
+
+/// The informations gathered at the end of an iteration of the search,
+/// reported to the callback given to Engine::on_iteration().
+#[derive(Clone, Debug)]
+pub struct IterationInfo {
+    /// The depth that was just completed.
+    pub depth: u8,
+    /// The score of the position, in pawns, from the point of view of the side to move.
+    pub score: f32,
+    /// The statistics of all threads since the search started.
+    pub stats: SearchStats,
+    /// The principal variation found at that depth.
+    pub pv: Vec<Move>,
+    /// The occupancy of the transposition table, in per mille.
+    pub hashfull: u16,
+}
+
+// ================================ traits impl
+
+impl fmt::Display for IterationInfo {
+    /// Formats the informations as an uci "info" line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "info depth {} score {}", self.depth, uci::format_score(self.score))?;
+        write!(f, " nodes {} nps {} hashfull {} time {} pv", self.stats.nodes, self.stats.nps(), self.hashfull, self.stats.elapsed.as_millis())?;
+        for mv in &self.pv {
+            write!(f, " {}", mv)?;
+        }
+
+        Ok(())
+    }
+}
+
+//#################################################################################################
+//
+//                                       struct Reporter
+//
+//#################################################################################################
+
+/// The type of the callbacks called after each iteration of the search.
+type Callback = Box<dyn FnMut(&IterationInfo) + Send>;
+
+/// Holds the callback the search threads report their iterations to, if any.
+#[derive(Default)]
+pub(crate) struct Reporter(Mutex<Option<Callback>>);
+
+// ================================ pub(crate) impl
+
+impl Reporter {
+    /// Calls the callback, if there is one, with the given infos.
+    #[inline]
+    pub(crate) fn report(&self, info: &IterationInfo) {
+        if let Some(callback) = self.0.lock().unwrap().as_mut() {
+            callback(info);
+        }
+    }
+}
+
+// ================================ traits impl
+
+impl fmt::Debug for Reporter {
+    /// Closures can't be debugged, only print the name of the struct.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Reporter")
+    }
+}
+
 //#################################################################################################
 //
 //                                        struct Engine
@@ -189,6 +394,7 @@ impl fmt::Display for EngineStatus {
 #[derive(Debug)]
 pub struct Engine {
     info: Arc<GlobalInfo>,
+    net: Arc<Net>,
     handles: Vec<JoinHandle<()>>,
     book: Option<Book>,
     status: EngineStatus,
@@ -199,7 +405,18 @@ pub struct Engine {
 
 impl Engine {
     /// Initializes a new chess engine, working on a board.
+    /// The pseudo-random number generation is seeded with the current time,
+    /// use Engine::with_seed() for reproducible results.
     pub fn new(board: Board, book_path: Option<&str>, net_path: &str) -> Result<Engine> {
+        Engine::with_seed(board, book_path, net_path, utils::seed())
+    }
+
+    /// Initializes a new chess engine, working on a board, with an explicit seed.
+    /// That seed is the only source of randomness of the engine: the choice of book
+    /// moves and the draw values of each search thread are derived from it.
+    /// Note that with more than one search thread, the result of a search still
+    /// depends on how the threads are scheduled.
+    pub fn with_seed(board: Board, book_path: Option<&str>, net_path: &str, seed: u32) -> Result<Engine> {
         // The book that may be used to lookup moves.
         let book = if let Some(book_path) = book_path {
             Some(Book::open(Path::new(book_path))?)
@@ -210,22 +427,11 @@ impl Engine {
         // The neural network used for evaluation.
         let net = Net::load(Path::new(net_path))?;
 
-        // Construct the initial info object.
-        let info = Arc::new(GlobalInfo {
-            barrier: Barrier::new(params::NUM_SEARCH_THREAD + 1),
-            searching: AtomicBool::new(false),
-            stop: AtomicBool::new(false),
-            
-            table: TranspositionTable::new(),
-            search_depth: AtomicU8::new(0),
-            search_id: AtomicU8::new(0),
-            best_move: AtomicMove::default(),
-
-            board: RwLock::new(board),
-        });
+        // Construct the initial info object, the main thread waiting at the barrier as well.
+        let info = Arc::new(GlobalInfo::new(board, params::NUM_SEARCH_THREAD + 1, TranspositionTable::new()));
 
-        // The seed used for all pseudo-random number generation.
-        let mut seed = utils::seed();
+        // The seed used for all pseudo-random number generation, xorshift being stuck on zero.
+        let mut seed = if seed == 0 {0x2F6B_E4A1} else {seed};
 
         // Initializes the thread pool.
         let handles = (0..params::NUM_SEARCH_THREAD).map(|_| {
@@ -241,6 +447,7 @@ impl Engine {
 
         Ok(Engine {
             info,
+            net,
             handles,
             book,
             status: EngineStatus::Idling,
@@ -248,11 +455,146 @@ impl Engine {
         })
     }
 
+    /// Initializes a chess engine without a thread pool, for platforms without threads such
+    /// as wasm, with its network read from the bytes of a network file, a transposition table
+    /// of at most the given size in megabytes, and an explicit seed. Only the searches running
+    /// on the current thread may be used, start() always returns false without searching.
+    pub fn single_threaded(board: Board, net: &[u8], table_mb: usize, seed: u32) -> Result<Engine> {
+        let net = Net::from_bytes(net)?;
+        let info = Arc::new(GlobalInfo::new(board, 1, TranspositionTable::with_size_mb(table_mb)));
+
+        Ok(Engine {
+            info,
+            net,
+            handles: Vec::new(),
+            book: None,
+            status: EngineStatus::Idling,
+            seed: if seed == 0 {0x2F6B_E4A1} else {seed},
+        })
+    }
+
     /// Returns the current best move.
     pub fn poll(&self) -> &EngineStatus {
         &self.status
     }
 
+    /// Sets the callback called by the search threads each time a new depth is completed,
+    /// replacing the previous one. This is where uci "info" lines should be printed.
+    pub fn on_iteration(&mut self, callback: impl FnMut(&IterationInfo) + Send + 'static) {
+        *self.info.reporter.0.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Searches the current board to the given depth on the current thread, with alpha-beta and
+    /// a fresh table. This is independent from the thread pool used by start() and stop(), and
+    /// blocks until the search is over. Returns the best move and its score, in pawns from the
+    /// point of view of the side to move, or None if there are no legal moves.
+    pub fn search(&mut self, depth: u8) -> Option<(Move, f32)> {
+        self.search_with_stats(depth, &mut SearchStats::default())
+    }
+
+    /// Like search(), also adding the statistics of the search to the given ones,
+    /// so as to measure the work done to reach that depth.
+    pub fn search_with_stats(&mut self, depth: u8, stats: &mut SearchStats) -> Option<(Move, f32)> {
+        let seed = utils::xorshift32(&mut self.seed);
+
+        search::fixed_depth(self.info.standalone(), &self.net, depth, seed, stats)
+    }
+
+    /// Searches the current board to the depths 1 to max_depth in turn on the current thread,
+    /// each iteration trying the best move of the previous one first, and sharing a fresh table.
+    /// The callback is called with the informations of each completed iteration, for example to
+    /// print uci "info" lines. Blocks until the search is over. Returns the principal variation
+    /// of the last iteration, empty if there are no legal moves.
+    pub fn search_iterative(&mut self, max_depth: u8, callback: impl FnMut(&IterationInfo)) -> Vec<Move> {
+        let seed = utils::xorshift32(&mut self.seed);
+
+        search::iterative(self.info.standalone(), &self.net, max_depth, seed, callback)
+    }
+
+    /// Searches the current board to the given depth by splitting the root moves between
+    /// the given number of threads, each searching its moves on its own copy of the board.
+    /// This is independent from the thread pool used by start() and stop(), and blocks until
+    /// the search is over. Returns the best move and its score, or None if there are no legal moves.
+    pub fn parallel_root(&mut self, depth: u8, threads: usize) -> Option<(Move, f32)> {
+        let seed = utils::xorshift32(&mut self.seed);
+
+        search::parallel_root(self.info.standalone(), &self.net, depth, threads, seed).first().copied()
+    }
+
+    /// Returns a move for the current board played by a weakened engine, for casual opponents.
+    /// The strength goes from 0 (any legal move may be played) to MAX_STRENGTH (the best
+    /// move of a shallow search is always played), higher values being clamped. Blocks
+    /// until the search is over. Returns None if there are no legal moves.
+    pub fn weak_move(&mut self, strength: u8) -> Option<Move> {
+        search::weak_move(self.info.standalone(), &self.net, strength, &mut self.seed)
+    }
+
+    /// Sets the contempt, in centipawns, taken into account from the next search on. The
+    /// engine then scores draws as if it was that much better than it's opponent: with a
+    /// positive contempt it avoids draws, with a negative one it seeks them. It is relative
+    /// to the side to move when the search starts, whichever color that is. Defaults to 0.
+    pub fn set_contempt(&mut self, contempt: i32) {
+        self.info.contempt.store(contempt, Ordering::Relaxed);
+    }
+
+    /// Returns the contempt, in centipawns.
+    pub fn get_contempt(&self) -> i32 {
+        self.info.contempt.load(Ordering::Relaxed)
+    }
+
+    /// Restricts the searches to the given moves at the root (uci's "go searchmoves"), for
+    /// analysis or forced openings, be they started with start() or running on the current
+    /// thread. An empty slice lifts the restriction. Like write_board(),
+    /// this stops the search if it is on and resets the search informations. Moves that are
+    /// not legal in the position searched are ignored, and if none of them are, the
+    /// restriction is ignored as well.
+    pub fn set_root_moves(&mut self, moves: &[Move]) {
+        drop(self.write_board());
+        *self.info.root_moves.write().unwrap() = moves.to_vec();
+    }
+
+    /// Resizes the transposition table of the thread pool to at most the given size in
+    /// megabytes, its number of entries being rounded down to a power of two. Like
+    /// write_board(), this stops the search if it is on and resets the search informations.
+    /// The table is emptied. Defaults to 32 MB, rounded down as well. The searches running on
+    /// the current thread, such as search(), use fresh tables of that size too.
+    pub fn set_table_size(&mut self, mb: usize) {
+        drop(self.write_board());
+
+        // SAFE: the search is stopped, the threads of the pool are waiting at the barrier.
+        unsafe {self.info.table.resize_mb(mb)};
+    }
+
+    /// Empties the transposition table of the thread pool, for example before a new game. Like
+    /// write_board(), this stops the search if it is on and resets the search informations.
+    pub fn clear_table(&mut self) {
+        drop(self.write_board());
+        self.info.table.clear();
+    }
+
+    /// Sets the time manager of the next searches started with start(), or None for searches
+    /// that last until stop() is called. Once the time is up, the search threads stop and
+    /// is_time_up() returns true: stop() must still be called to get the best move.
+    pub fn set_time_manager(&mut self, time: Option<TimeManager>) {
+        *self.info.time.write().unwrap() = time;
+    }
+
+    /// Returns true if the time given to the search is up, the search threads having stopped.
+    pub fn is_time_up(&self) -> bool {
+        self.info.is_time_up()
+    }
+
+    /// Returns the statistics of the current or last search started with start(). Only the
+    /// iterations completed by the search threads are accounted for.
+    pub fn stats(&self) -> SearchStats {
+        self.info.stats()
+    }
+
+    /// Returns the size of the transposition table of the thread pool, in megabytes rounded up.
+    pub fn get_table_size(&self) -> usize {
+        self.info.table.size_mb()
+    }
+
     /// Returns a read lock to the board.
     pub fn read_board(&self) -> RwLockReadGuard<'_, Board> {
         self.info.board.read().unwrap()
@@ -265,13 +607,18 @@ impl Engine {
     /// May return true, meaning the engine has started thinking and
     /// will need to be stopped and polled whenever we want some results.
     pub fn start(&mut self) -> bool {
-        // If already searching, return.
-        if self.info.is_searching() {
+        // If already searching, or without threads to search, return.
+        if self.info.is_searching() || self.handles.is_empty() {
             return false;
         }
 
-        // If a match is found in a book, return it.
-        if let Some(mv) = self.lookup() {
+        // If a match is found in a book, and the root moves allow it, return it.
+        let book_move = self.lookup().filter(|mv| {
+            let root_moves = self.info.root_moves.read().unwrap();
+            root_moves.is_empty() || root_moves.contains(mv)
+        });
+
+        if let Some(mv) = book_move {
             self.status = EngineStatus::BookMove(mv);
             return false;
         }
@@ -279,6 +626,16 @@ impl Engine {
         // Set the engine as thinking.
         self.status = EngineStatus::Thinking;
 
+        // Entries of the previous searches are now replaced first.
+        self.info.table.new_search();
+
+        // Reset the statistics reported at each iteration.
+        self.info.nodes.store(0, Ordering::Relaxed);
+        self.info.qnodes.store(0, Ordering::Relaxed);
+        self.info.tt_hits.store(0, Ordering::Relaxed);
+        *self.info.start.lock().unwrap() = utils::Clock::start();
+        self.info.time_up.store(false, Ordering::Relaxed);
+
         // Set the searching flag and wait at the barrier with 
         // the other threads that are already waiting.
         self.info.searching.store(true, Ordering::Release);
@@ -387,4 +744,118 @@ impl Drop for Engine {
             handle.join().ok();
         }
     }
-}
\ No newline at end of file
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chess::board::Board;
+
+    use crate::eval;
+
+    use super::{Engine, SearchStats};
+
+    /// The path to the network shipped with the engine.
+    const NET_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/nets/nnue.bin");
+
+    /// Returns an engine without thread pool, searching the given position.
+    fn engine(fen: &str) -> Engine {
+        chess::init();
+
+        let net = std::fs::read(NET_PATH).unwrap();
+        Engine::single_threaded(Board::new(fen).unwrap(), &net, 1, 1).unwrap()
+    }
+
+    #[test]
+    fn root_moves() {
+        let mut engine = engine("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let allowed = {
+            let board = engine.read_board();
+            [board.parse_move("a2a3").unwrap(), board.parse_move("h2h4").unwrap()]
+        };
+        engine.set_root_moves(&allowed);
+
+        let (mv, _) = engine.search(3).unwrap();
+        assert!(allowed.contains(&mv));
+
+        let pv = engine.search_iterative(3, |_| ());
+        assert!(allowed.contains(&pv[0]));
+
+        let (mv, _) = engine.parallel_root(2, 2).unwrap();
+        assert!(allowed.contains(&mv));
+
+        for _ in 0..8 {
+            assert!(allowed.contains(&engine.weak_move(0).unwrap()));
+        }
+    }
+
+    #[test]
+    fn nps() {
+        let stats = SearchStats {nodes: 3000, elapsed: Duration::from_millis(1500), ..SearchStats::default()};
+        assert_eq!(stats.nps(), 2000);
+
+        // No time elapsed yet, nor any node searched.
+        let stats = SearchStats {nodes: 3000, ..SearchStats::default()};
+        assert_eq!(stats.nps(), 3_000_000_000);
+        assert_eq!(SearchStats::default().nps(), 0);
+    }
+
+    #[test]
+    fn search_stats() {
+        let mut engine = engine("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+
+        let mut stats = SearchStats::default();
+        engine.search_with_stats(3, &mut stats);
+        assert!(stats.nodes > 0);
+        assert!(stats.qnodes <= stats.nodes);
+        assert!(stats.tt_hits <= stats.nodes);
+
+        // The statistics of the next search are added.
+        let first = stats;
+        engine.search_with_stats(3, &mut stats);
+        assert!(stats.nodes > first.nodes);
+        assert!(stats.qnodes >= first.qnodes);
+        assert!(stats.elapsed >= first.elapsed);
+    }
+
+    #[test]
+    fn mate_in_one() {
+        let mut mating = engine("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+
+        // The mate is seen once the replies are searched.
+        for depth in 2..=4 {
+            let (mv, score) = mating.search(depth).unwrap();
+            assert_eq!(mv.to_string(), "a1a8");
+            assert_eq!(score, eval::MATE - 1.0);
+        }
+
+        // Once mated, there is nothing to search.
+        let mut mated = engine("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1");
+        assert!(mated.search(3).is_none());
+    }
+
+    #[test]
+    fn iterations() {
+        let mut engine = engine("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+
+        let mut infos = Vec::new();
+        let pv = engine.search_iterative(4, |info| infos.push(info.clone()));
+
+        // Once per depth, the statistics adding up from an iteration to the next.
+        assert_eq!(infos.iter().map(|info| info.depth).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        for pair in infos.windows(2) {
+            assert!(pair[1].stats.nodes > pair[0].stats.nodes);
+            assert!(pair[1].stats.elapsed >= pair[0].stats.elapsed);
+        }
+
+        assert_eq!(pv, infos[3].pv);
+        assert!(!pv.is_empty());
+    }
+}
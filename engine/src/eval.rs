@@ -9,12 +9,13 @@ use std::sync::Arc;
 use anyhow::{Error, Result};
 
 use chess::board::Board;
+use chess::castle_rights::CastleSide;
 use chess::moves::Move;
 use chess::piece::Piece;
 use chess::prelude::Color;
 use chess::square::Square;
 
-use crate::utils;
+use crate::{params, utils};
 
 /// Returns the heuristic value of a piece, in pawns.
 #[inline]
@@ -29,6 +30,215 @@ pub(crate) const fn value_of(piece: Piece) -> f32 {
     }
 }
 
+/// The score of checkmating the opponent right now, in pawns. Mate scores are that value minus
+/// the number of plies from the root to the mate, so that shorter mates are preferred.
+pub(crate) const MATE: f32 = value_of(Piece::King);
+
+/// Returns true if the score is a mate score, for either side.
+#[inline]
+pub(crate) fn is_mate(score: f32) -> bool {
+    score.abs() >= MATE - params::MAX_DEPTH as f32
+}
+
+/// Converts a mate score relative to the root into one relative to a node that many plies
+/// away from the root, to be stored in the table. Other scores are left untouched.
+#[inline]
+pub(crate) fn score_to_node(score: f32, ply: u8) -> f32 {
+    if !is_mate(score) {
+        score
+    } else if score > 0.0 {
+        score + f32::from(ply)
+    } else {
+        score - f32::from(ply)
+    }
+}
+
+/// Converts a mate score relative to a node that many plies away from the root, as stored in
+/// the table, into one relative to the root. The inverse of score_to_node().
+#[inline]
+pub(crate) fn score_from_node(score: f32, ply: u8) -> f32 {
+    if !is_mate(score) {
+        score
+    } else if score > 0.0 {
+        score - f32::from(ply)
+    } else {
+        score + f32::from(ply)
+    }
+}
+
+//#################################################################################################
+//
+//                                        fn evaluate()
+//
+//#################################################################################################
+
+/// The middlegame piece-square tables, in centipawns, indexed by piece then square from white's
+/// point of view, starting from A8: as the board is printed. Material is not included.
+const PST_MG: [[i32; 64]; 6] = [
+    // Pawn.
+    [
+          0,   0,   0,   0,   0,   0,  0,   0,
+         98, 134,  61,  95,  68, 126, 34, -11,
+         -6,   7,  26,  31,  65,  56, 25, -20,
+        -14,  13,   6,  21,  23,  12, 17, -23,
+        -27,  -2,  -5,  12,  17,   6, 10, -25,
+        -26,  -4,  -4, -10,   3,   3, 33, -12,
+        -35,  -1, -20, -23, -15,  24, 38, -22,
+          0,   0,   0,   0,   0,   0,  0,   0,
+    ],
+    // Knight.
+    [
+       -167, -89, -34, -49,  61, -97, -15, -107,
+        -73, -41,  72,  36,  23,  62,   7,  -17,
+        -47,  60,  37,  65,  84, 129,  73,   44,
+         -9,  17,  19,  53,  37,  69,  18,   22,
+        -13,   4,  16,  13,  28,  19,  21,   -8,
+        -23,  -9,  12,  10,  19,  17,  25,  -16,
+        -29, -53, -12,  -3,  -1,  18, -14,  -19,
+       -105, -21, -58, -33, -17, -28, -19,  -23,
+    ],
+    // Bishop.
+    [
+        -29,   4, -82, -37, -25, -42,   7,  -8,
+        -26,  16, -18, -13,  30,  59,  18, -47,
+        -16,  37,  43,  40,  35,  50,  37,  -2,
+         -4,   5,  19,  50,  37,  37,   7,  -2,
+         -6,  13,  13,  26,  34,  12,  10,   4,
+          0,  15,  15,  15,  14,  27,  18,  10,
+          4,  15,  16,   0,   7,  21,  33,   1,
+        -33,  -3, -14, -21, -13, -12, -39, -21,
+    ],
+    // Rook.
+    [
+         32,  42,  32,  51,  63,   9,  31,  43,
+         27,  32,  58,  62,  80,  67,  26,  44,
+         -5,  19,  26,  36,  17,  45,  61,  16,
+        -24, -11,   7,  26,  24,  35,  -8, -20,
+        -36, -26, -12,  -1,   9,  -7,   6, -23,
+        -45, -25, -16, -17,   3,   0,  -5, -33,
+        -44, -16, -20,  -9,  -1,  11,  -6, -71,
+        -19, -13,   1,  17,  16,   7, -37, -26,
+    ],
+    // Queen.
+    [
+        -28,   0,  29,  12,  59,  44,  43,  45,
+        -24, -39,  -5,   1, -16,  57,  28,  54,
+        -13, -17,   7,   8,  29,  56,  47,  57,
+        -27, -27, -16, -16,  -1,  17,  -2,   1,
+         -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+        -14,   2, -11,  -2,  -5,   2,  14,   5,
+        -35,  -8,  11,   2,   8,  15,  -3,   1,
+         -1, -18,  -9,  10, -15, -25, -31, -50,
+    ],
+    // King.
+    [
+        -65,  23,  16, -15, -56, -34,   2,  13,
+         29,  -1, -20,  -7,  -8,  -4, -38, -29,
+         -9,  24,   2, -16, -20,   6,  22, -22,
+        -17, -20, -12, -27, -30, -25, -14, -36,
+        -49,  -1, -27, -39, -46, -44, -33, -51,
+        -14, -14, -22, -46, -44, -30, -15, -27,
+          1,   7,  -8, -64, -43, -16,   9,   8,
+        -15,  36,  12, -54,   8, -28,  24,  14,
+    ],
+];
+
+/// The endgame piece-square tables, laid out as PST_MG.
+const PST_EG: [[i32; 64]; 6] = [
+    // Pawn.
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+        178, 173, 158, 134, 147, 132, 165, 187,
+         94, 100,  85,  67,  56,  53,  82,  84,
+         32,  24,  13,   5,  -2,   4,  17,  17,
+         13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+          4,   7,  -6,   1,   0,  -5,  -1,  -8,
+         13,   8,   8,  10,  13,   0,   2,  -7,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    // Knight.
+    [
+        -58, -38, -13, -28, -31, -27, -63, -99,
+        -25,  -8, -25,  -2,  -9, -25, -24, -52,
+        -24, -20,  10,   9,  -1,  -9, -19, -41,
+        -17,   3,  22,  22,  22,  11,   8, -18,
+        -18,  -6,  16,  25,  16,  17,   4, -18,
+        -23,  -3,  -1,  15,  10,  -3, -20, -22,
+        -42, -20, -10,  -5,  -2, -20, -23, -44,
+        -29, -51, -23, -15, -22, -18, -50, -64,
+    ],
+    // Bishop.
+    [
+        -14, -21, -11,  -8,  -7,  -9, -17, -24,
+         -8,  -4,   7, -12,  -3, -13,  -4, -14,
+          2,  -8,   0,  -1,  -2,   6,   0,   4,
+         -3,   9,  12,   9,  14,  10,   3,   2,
+         -6,   3,  13,  19,   7,  10,  -3,  -9,
+        -12,  -3,   8,  10,  13,   3,  -7, -15,
+        -14, -18,  -7,  -1,   4,  -9, -15, -27,
+        -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+    ],
+    // Rook.
+    [
+         13,  10,  18,  15,  12,  12,   8,   5,
+         11,  13,  13,  11,  -3,   3,   8,   3,
+          7,   7,   7,   5,   4,  -3,  -5,  -3,
+          4,   3,  13,   1,   2,   1,  -1,   2,
+          3,   5,   8,   4,  -5,  -6,  -8, -11,
+         -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+         -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+         -9,   2,   3,  -1,  -5, -13,   4, -20,
+    ],
+    // Queen.
+    [
+         -9,  22,  22,  27,  27,  19,  10,  20,
+        -17,  20,  32,  41,  58,  25,  30,   0,
+        -20,   6,   9,  49,  47,  35,  19,   9,
+          3,  22,  24,  45,  57,  40,  57,  36,
+        -18,  28,  19,  47,  31,  34,  39,  23,
+        -16, -27,  15,   6,   9,  17,  10,   5,
+        -22, -23, -30, -16, -16, -23, -36, -32,
+        -33, -28, -22, -43,  -5, -32, -20, -41,
+    ],
+    // King.
+    [
+        -74, -35, -18, -18, -11,  15,   4, -17,
+        -12,  17,  14,  17,  17,  38,  23,  11,
+         10,  17,  23,  15,  20,  45,  44,  13,
+         -8,  22,  24,  27,  26,  33,  26,   3,
+        -18,  -4,  21,  24,  27,  23,   9, -11,
+        -19,  -3,  11,  21,  23,  16,   7,  -9,
+        -27, -11,   4,  13,  14,   4,  -5, -17,
+        -53, -34, -21, -11, -28, -14, -24, -43,
+    ],
+];
+
+/// A static evaluation of the board, in pawns from the point of view of the side to move, that
+/// does not need any network: the material and piece-square tables, tapered between the middlegame
+/// and the endgame values according to the game phase.
+pub fn evaluate(board: &Board) -> f32 {
+    let mut mg = board.get_material_mg();
+    let mut eg = board.get_material_eg();
+
+    for &piece in Piece::PIECES.iter() {
+        for sq in board.get_bitboard(Color::White, piece).iter_squares() {
+            let i = usize::from(sq) ^ 56;
+            mg += PST_MG[usize::from(piece)][i];
+            eg += PST_EG[usize::from(piece)][i];
+        }
+        for sq in board.get_bitboard(Color::Black, piece).iter_squares() {
+            let i = usize::from(sq);
+            mg -= PST_MG[usize::from(piece)][i];
+            eg -= PST_EG[usize::from(piece)][i];
+        }
+    }
+
+    let phase = i32::from(board.get_phase());
+    let score = (mg * phase + eg * (i32::from(Piece::MAX_PHASE) - phase)) as f32 / (100.0 * f32::from(Piece::MAX_PHASE));
+
+    if board.get_side_to_move() == Color::White {score} else {-score}
+}
+
 //#################################################################################################
 //
 //                                         struct Net
@@ -54,50 +264,65 @@ impl Net {
     /// Loads a neural network from a file located at the given path.
     pub(crate) fn load(path: &Path) -> Result<Arc<Net>> {
         let mut file = File::open(path).map_err(|_| Error::msg("Cannot open network file."))?;
+        Net::read(&mut file)
+    }
+
+    /// Loads a neural network from the bytes of a network file, for platforms without files.
+    pub(crate) fn from_bytes(mut bytes: &[u8]) -> Result<Arc<Net>> {
+        Net::read(&mut bytes)
+    }
+}
+
+// ================================ impl
 
-        fn read_f32(file: &mut File, x: &mut f32) -> Result<()> {
+impl Net {
+    /// Reads a neural network from the content of a network file.
+    fn read(reader: &mut impl Read) -> Result<Arc<Net>> {
+        fn read_f32(reader: &mut impl Read, x: &mut f32) -> Result<()> {
             let mut buf = [0; 4];
-            file.read(&mut buf).map_err(|_| Error::msg("Not enough bytes in network file."))?;
+            reader.read_exact(&mut buf).map_err(|_| Error::msg("Not enough bytes in network file."))?;
             *x = f32::from_be_bytes(buf);
             Ok(())
         }
 
-        fn read_vec<const N: usize>(file: &mut File, vec: &mut [f32; N]) -> Result<()> {
+        fn read_vec<const N: usize>(reader: &mut impl Read, vec: &mut [f32; N]) -> Result<()> {
             for i in 0..N {
-                read_f32(file, &mut vec[i])?;
+                read_f32(reader, &mut vec[i])?;
             }
             Ok(())
         }
 
-        fn read_mat<const N: usize, const M: usize>(file: &mut File, mat: &mut [[f32; M]; N]) -> Result<()> {
+        fn read_mat<const N: usize, const M: usize>(reader: &mut impl Read, mat: &mut [[f32; M]; N]) -> Result<()> {
             for i in 0..N {
-                read_vec(file, &mut mat[i])?;
+                read_vec(reader, &mut mat[i])?;
             }
             Ok(())
         }
 
         // Done with manual allocation so as not to overflow the stack with the Net struct.
-        // SAFE: Arc is specified to accept pointers allocated with std::alloc::alloc()
-        Ok(unsafe {
-            let ptr = alloc::alloc(Layout::new::<Net>()) as *mut Net;
-
-            read_mat(&mut file, &mut (*ptr).w0)?;
-            read_vec(&mut file, &mut (*ptr).b0)?;
-            read_mat(&mut file, &mut (*ptr).w1)?;
-            read_vec(&mut file, &mut (*ptr).b1)?;
-            read_mat(&mut file, &mut (*ptr).w2)?;
-            read_vec(&mut file, &mut (*ptr).b2)?;
-            read_vec(&mut file, &mut (*ptr).w3)?;
-            read_f32(&mut file, &mut (*ptr).b3)?;
-
-            Arc::from_raw(ptr)
-        })
+        // SAFE: Box accepts pointers allocated with std::alloc::alloc() and the layout of its
+        // type, and zeroed floats are valid. The network is freed on errors, with the box.
+        let mut net: Box<Net> = unsafe {
+            let layout = Layout::new::<Net>();
+            let ptr = alloc::alloc_zeroed(layout) as *mut Net;
+            if ptr.is_null() {
+                alloc::handle_alloc_error(layout);
+            }
+            Box::from_raw(ptr)
+        };
+
+        read_mat(reader, &mut net.w0)?;
+        read_vec(reader, &mut net.b0)?;
+        read_mat(reader, &mut net.w1)?;
+        read_vec(reader, &mut net.b1)?;
+        read_mat(reader, &mut net.w2)?;
+        read_vec(reader, &mut net.b2)?;
+        read_vec(reader, &mut net.w3)?;
+        read_f32(reader, &mut net.b3)?;
+
+        Ok(Arc::from(net))
     }
-}
-
-// ================================ impl
 
-impl Net {
     /// Must be kept in sync with the constant of the same name in the training script.
     const SIZE: usize = 128;
 
@@ -252,13 +477,8 @@ impl Eval {
 
             // If it's a castle, update the position of the rook on the other side's accumulator.
             if mv.is_castle() {
-                let (from, to) = match mv.to() {
-                    Square::G1 => (Square::H1, Square::F1),
-                    Square::C1 => (Square::A1, Square::D1),
-                    Square::G8 => (Square::H8, Square::F8),
-                    Square::C8 => (Square::A8, Square::D8),
-                    _ => unreachable!(),
-                };
+                let (_, side) = CastleSide::from_king_to(mv.to()).unwrap();
+                let (from, to) = (board.get_castle_setup().rook(color, side), side.rook_to(color));
 
                 if color == Color::White {
                     let feature_1 = self.feature_b(color, piece, from);
@@ -446,4 +666,53 @@ impl Eval {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use chess::board::Board;
+
+    use crate::eval;
+
+    #[test]
+    fn evaluate() {
+        chess::init();
+
+        // Symmetrical positions are equal.
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(eval::evaluate(&board), 0.0);
+
+        // Mirrored positions have opposite scores, for the side to move.
+        let board = Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mirrored = Board::new("r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1").unwrap();
+        assert!((eval::evaluate(&board) - eval::evaluate(&mirrored)).abs() < 1e-4);
+
+        // Up a queen is good for the side to move, bad for the other one.
+        let board = Board::new("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert!(eval::evaluate(&board) > 8.0);
+        let board = Board::new("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert!(eval::evaluate(&board) < -8.0);
+    }
+
+    #[test]
+    fn mate_scores() {
+        // Mating in 3 plies from the root is mating in 1 ply from a node 2 plies away.
+        let score = eval::MATE - 3.0;
+        assert_eq!(eval::score_to_node(score, 2), eval::MATE - 1.0);
+        assert_eq!(eval::score_from_node(eval::MATE - 1.0, 2), score);
+        assert_eq!(eval::score_from_node(eval::score_to_node(-score, 2), 2), -score);
+
+        // Probed at another ply, the mate is as far from the node.
+        assert_eq!(eval::score_from_node(eval::score_to_node(score, 2), 4), eval::MATE - 5.0);
+
+        // Other scores are untouched.
+        assert_eq!(eval::score_to_node(1.5, 7), 1.5);
+        assert_eq!(eval::score_from_node(-1.5, 7), -1.5);
+    }
+}
@@ -16,9 +16,13 @@ pub(crate) struct Heuristics {
 
 impl Heuristics {
     #[inline]
-    /// Store a new killer move, replacing the oldest one.
+    /// Store a new killer move, replacing the oldest one. A move that already is
+    /// a killer is not stored twice, so as to keep two different killers.
     pub(crate) fn store_killer(&mut self, mv: Move, depth: u8) {
         let depth = usize::from(depth);
+        if self.killers[depth].contains(&Some(mv)) {
+            return;
+        }
         self.killers[depth][0] = self.killers[depth][1];
         self.killers[depth][1] = Some(mv);
     }
@@ -30,6 +34,15 @@ impl Heuristics {
         self.history[usize::from(mv.from())][usize::from(mv.to())] += depth * depth;
     }
 
+    #[inline]
+    /// Halves the history, to be done at the start of each search: moves that were good
+    /// in the previous searches still come first, but the scores never saturate.
+    pub(crate) fn age(&mut self) {
+        for score in self.history.iter_mut().flatten() {
+            *score /= 2.0;
+        }
+    }
+
     #[inline]
     /// Rates a given quiet move.
     pub(crate) fn rate(&self, mv: Move, depth: u8) -> RatedMove {
@@ -7,10 +7,22 @@ mod heuristics;
 mod movepick;
 mod search;
 mod table;
+mod time;
 mod utils;
 
+pub mod uci;
+
 /// The version of the engine.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-// Export the Engine struct.
-pub use self::engine::Engine;
\ No newline at end of file
+/// The maximum strength accepted by Engine::weak_move().
+pub const MAX_STRENGTH: u8 = params::MAX_STRENGTH;
+
+// Export the Engine struct and the informations and statistics it reports.
+pub use self::engine::{Engine, IterationInfo, SearchStats};
+
+// Export the static evaluation, that needs no network.
+pub use self::eval::evaluate;
+
+// Export the time management of the search.
+pub use self::time::TimeManager;
\ No newline at end of file
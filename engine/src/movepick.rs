@@ -105,6 +105,8 @@ impl MovePicker {
     }
 
     /// Returns the next pseudo-legal move to try, or None if there is no more moves for this position.
+    /// Once None was returned, the picker stays in it's stop state and every subsequent call
+    /// returns None as well, without generating anything.
     #[inline]
     pub(crate) fn next(&mut self, board: &Board, heuristics: &Heuristics, depth: u8, buffer: &mut Vec<RatedMove>) -> Option<Move> {
         // If there were any leftovers move from a deeper node's MovePicker: forget them.
@@ -181,7 +183,7 @@ impl MovePicker {
                 // All captures under single check.
                 MovePickerState::CheckCaptures {mask} => {
                     movegen::gen_pawn_captures(board, |mv| if mask.contains(mv.to()) {buffer.push(RatedMove::capture(Piece::Pawn, mv))});
-                    movegen::gen_en_passant(board, |mv| if mask.contains(mv.to()) {buffer.push(RatedMove::capture(Piece::Pawn, mv))});
+                    movegen::gen_en_passant(board, |mv| buffer.push(RatedMove::capture(Piece::Pawn, mv)));
                     movegen::gen_captures(board, |piece, mv| if mask.contains(mv.to()) {buffer.push(RatedMove::capture(piece, mv))});
                     movegen::gen_king_captures(board, |mv| buffer.push(RatedMove::capture(Piece::King, mv)));
                     MovePickerState::CheckOthers {mask}
@@ -207,6 +209,7 @@ impl MovePicker {
                     MovePickerState::Stop
                 },
 
+                // Nothing left: stay in that state, so that exhausted pickers keep returning None.
                 MovePickerState::Stop => return false,
             };
 
@@ -288,8 +291,10 @@ impl Captures {
         let checkers = board.get_checkers();
 
         if checkers.empty() {
-            // No checkers, do all captures, including promotion, en passant, pawn and king ones.
+            // No checkers, do all captures, including promotion, en passant, pawn and king ones,
+            // as well as queen promotions.
             movegen::gen_promote_captures(board, &Piece::PROMOTES, |mv| buffer.push(RatedMove::promote_capture(mv)));
+            movegen::gen_promotes(board, &[Piece::Queen], |mv| buffer.push(RatedMove::promote(mv)));
             movegen::gen_pawn_captures(board, |mv| buffer.push(RatedMove::capture(Piece::Pawn, mv)));
             movegen::gen_en_passant(board, |mv| buffer.push(RatedMove::capture(Piece::Pawn, mv)));
             movegen::gen_captures(board, |piece, mv| buffer.push(RatedMove::capture(piece, mv)));
@@ -304,8 +309,9 @@ impl Captures {
             let mask = BitBoard::between(board.king_sq(board.get_side_to_move()), checker) | checkers;
 
             movegen::gen_promote_captures(board, &Piece::PROMOTES, |mv| if mask.contains(mv.to()) {buffer.push(RatedMove::promote_capture(mv))});
+            movegen::gen_promotes(board, &[Piece::Queen], |mv| if mask.contains(mv.to()) {buffer.push(RatedMove::promote(mv))});
             movegen::gen_pawn_captures(board, |mv| if mask.contains(mv.to()) {buffer.push(RatedMove::capture(Piece::Pawn, mv))});
-            movegen::gen_en_passant(board, |mv| if mask.contains(mv.to()) {buffer.push(RatedMove::capture(Piece::Pawn, mv))});
+            movegen::gen_en_passant(board, |mv| buffer.push(RatedMove::capture(Piece::Pawn, mv)));
             movegen::gen_captures(board, |piece, mv| if mask.contains(mv.to()) {buffer.push(RatedMove::capture(piece, mv))});
             movegen::gen_king_captures(board, |mv| buffer.push(RatedMove::capture(Piece::King, mv)));
         }
@@ -318,6 +324,8 @@ impl Captures {
         }
     }
 
+    /// Returns the next capture to try, or None if there is no more. Every call following the
+    /// first None returns None as well.
     #[inline]
     pub(crate) fn next(&mut self, buffer: &mut Vec<RatedMove>) -> Option<Move> {
         if self.start == self.end {
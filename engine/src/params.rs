@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 /// The size of the transposition table in bytes. Not exact.
 pub(crate) const TABLE_SIZE: usize = 33554432;
 
@@ -10,5 +12,90 @@ pub(crate) const ASPIRATION_WINDOW: &[f32] = &[0.01, 0.05, 2.5, f32::INFINITY];
 /// The maximum search depth.
 pub(crate) const MAX_DEPTH: usize = 32;
 
+/// The depth reductions of late moves, indexed by the remaining depth then the number of moves
+/// searched before, both capped to the size of the table. Grows with both, like their logarithms.
+pub(crate) const LATE_MOVE_REDUCTIONS: [[u8; 32]; 32] = {
+    let mut table = [[0; 32]; 32];
+
+    // The first few moves and shallow nodes are never reduced.
+    let mut depth = 3;
+    while depth < 32 {
+        let mut count = 3;
+        while count < 32 {
+            let log_depth = 31 - (depth as u32).leading_zeros();
+            let log_count = 31 - (count as u32).leading_zeros();
+            table[depth][count] = (1 + log_depth * log_count / 3) as u8;
+            count += 1;
+        }
+        depth += 1;
+    }
+
+    table
+};
+
+/// Returns the depth reduction of a late move, searched after move_count other moves at a
+/// node of the given remaining depth. It always leaves at least one ply to search.
+#[inline]
+pub(crate) fn late_move_reduction(depth: u8, move_count: usize) -> u8 {
+    let reduction = LATE_MOVE_REDUCTIONS[usize::from(depth).min(31)][move_count.min(31)];
+    reduction.min(depth.saturating_sub(2))
+}
+
+/// The time kept aside on each move for the communication with the gui.
+pub(crate) const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+/// The number of moves the remaining time is spread over, when the time control gives none.
+pub(crate) const MOVES_TO_GO: u32 = 30;
+
+/// The number of moves of the opening, during which more time is used.
+pub(crate) const OPENING_MOVES: u32 = 20;
+
+/// Below that much time left, the time used for each move shrinks faster.
+pub(crate) const TIME_PRESSURE: Duration = Duration::from_secs(10);
+
+/// How much longer than the soft deadline the hard one is, at most.
+pub(crate) const HARD_TIME_RATIO: u32 = 3;
+
+/// The number of nodes a search thread searches between each time check.
+pub(crate) const TIME_CHECK_NODES: u64 = 4096;
+
 /// Used during quiescient search for delta pruning.
-pub(crate) const DELTA: f32 = 2.0;
\ No newline at end of file
+pub(crate) const DELTA: f32 = 2.0;
+
+/// The maximum strength of the weakened engine, playing at full depth-limited strength.
+pub(crate) const MAX_STRENGTH: u8 = 10;
+
+/// The margin below the best score within which the weakened engine may pick a
+/// move, for each level of strength below the maximum.
+pub(crate) const WEAK_MARGIN: f32 = 0.3;
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::late_move_reduction;
+
+    #[test]
+    fn late_move_reductions() {
+        for depth in 0..=u8::MAX {
+            for move_count in 0..64 {
+                let reduction = late_move_reduction(depth, move_count);
+
+                // The first moves and shallow nodes are not reduced.
+                if depth < 3 || move_count < 3 {
+                    assert_eq!(reduction, 0);
+                }
+
+                // At least one ply is left to search, and later moves are reduced at least as much.
+                assert!(reduction <= depth.saturating_sub(2));
+                assert!(reduction >= late_move_reduction(depth, move_count.saturating_sub(1)));
+            }
+        }
+
+        assert!(late_move_reduction(16, 16) > 1);
+    }
+}
@@ -1,16 +1,21 @@
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 use chess::board::Board;
+use chess::color::Color;
+use chess::movegen;
 use chess::moves::Move;
 use chess::piece::Piece;
 
-use crate::engine::GlobalInfo;
+use crate::engine::{GlobalInfo, IterationInfo, SearchStats};
 use crate::eval::{Eval, Net};
 use crate::heuristics::Heuristics;
 use crate::{eval, utils};
 use crate::movepick::{Captures, MovePicker, RatedMove};
 use crate::params;
 use crate::table::{TableEntry, TableEntryFlag};
+use crate::time::TimeManager;
 
 /// A struct holding all the necessary information for a search thread.
 #[derive(Debug)]
@@ -24,7 +29,13 @@ pub(crate) struct Search {
     
     info: Arc<GlobalInfo>,
     depth: u8,
+    stats: SearchStats,
     seed: u32,
+
+    root_color: Color,
+    root_moves: Vec<Move>,
+    contempt: f32,
+    time: Option<TimeManager>,
 }
 
 // ================================ pub(crate) impl
@@ -42,10 +53,41 @@ impl Search {
             
             info,
             depth: 0,
+            stats: SearchStats::default(),
             seed,
+
+            root_color: Color::White,
+            root_moves: Vec::new(),
+            contempt: 0.0,
+            time: None,
         }
     }
 
+    /// Sets the board to search from, resetting what needs to be.
+    pub(crate) fn set_board(&mut self, board: Board) {
+        self.board = board;
+        self.reset();
+        self.set_root();
+    }
+
+    /// Returns the score of the given root move, searched to the given depth with a full window.
+    pub(crate) fn search_root_move(&mut self, mv: Move, depth: u8) -> f32 {
+        self.depth += 1;
+        self.eval.do_move(&mut self.board, mv);
+        let score = -self.alpha_beta(f32::NEG_INFINITY, f32::INFINITY, true, depth - 1, depth);
+        self.eval.undo_move(&mut self.board, mv);
+        self.depth -= 1;
+
+        score
+    }
+
+    /// Searches the board to the given depth with a full window. Returns the best move and
+    /// its score, or None if there are no legal moves.
+    pub(crate) fn search_depth(&mut self, depth: u8) -> Option<(Move, f32)> {
+        let score = self.alpha_beta(f32::NEG_INFINITY, f32::INFINITY, true, depth, depth);
+        self.best_move.map(|mv| (mv, score))
+    }
+
     /// The loop run by threads
     pub(crate) fn thread_main(&mut self) {
         loop {
@@ -69,6 +111,62 @@ impl Search {
 // ================================ impl
 
 impl Search {
+    /// Stores the side to move at the root, the legal moves the search is restricted
+    /// to at the root, the contempt and the time manager of the search.
+    fn set_root(&mut self) {
+        self.root_color = self.board.get_side_to_move();
+        self.contempt = self.info.contempt();
+        self.time = self.info.time_manager();
+
+        let board = &self.board;
+        self.root_moves = self.info.root_moves();
+        self.root_moves.retain(|&mv| board.is_pseudo_legal(mv) && board.is_legal(mv));
+    }
+
+    /// Returns true if the move may be searched from the current node: any move
+    /// below the root, and only the allowed moves at the root.
+    #[inline]
+    fn is_root_move(&self, mv: Move) -> bool {
+        self.depth != 0 || self.root_moves.is_empty() || self.root_moves.contains(&mv)
+    }
+
+    /// The score of a draw for the side to move: the contempt is against the root side.
+    #[inline]
+    fn draw_score(&self) -> f32 {
+        if self.board.get_side_to_move() == self.root_color {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
+    /// Returns true if the search must stop, because it was told to or because its time is up.
+    #[inline]
+    fn is_stopped(&self) -> bool {
+        !self.info.is_searching() || self.info.is_time_up()
+    }
+
+    /// Counts a node, and every params::TIME_CHECK_NODES nodes, stops the search if past
+    /// its hard deadline. At least one iteration must be completed to have a best move.
+    #[inline]
+    fn count_node(&mut self) {
+        self.stats.nodes += 1;
+
+        if self.stats.nodes.is_multiple_of(params::TIME_CHECK_NODES) && self.info.search_depth() > 0 &&
+            self.time.is_some_and(|time| time.should_stop()) {
+            self.info.set_time_up();
+        }
+    }
+
+    /// Inserts the entry in the transposition table, except at the root when the search is
+    /// restricted to some root moves, as it would not hold for the unrestricted position.
+    #[inline]
+    fn store(&self, entry: TableEntry) {
+        if self.depth != 0 || self.root_moves.is_empty() {
+            self.info.get_table().insert(entry);
+        }
+    }
+
     /// Resets what needs to be after a new position is encountered.
     fn reset(&mut self) {
         self.best_move = None;
@@ -88,11 +186,23 @@ impl Search {
                 self.reset();
             }
         }
+
+        self.set_root();
+        self.heuristics.age();
         
+        // Nodes of an aborted iteration are not accounted for.
+        self.stats = SearchStats::default();
+
         // Compute first reference score.
-        let best_score = self.quiescence(f32::NEG_INFINITY, f32::INFINITY);
+        let mut best_score = self.quiescence(f32::NEG_INFINITY, f32::INFINITY);
         
         'main: loop {
+            // Don't start an iteration that would not end in time.
+            if self.info.search_depth() > 0 && self.time.is_some_and(|time| !time.can_start_iteration()) {
+                self.info.set_time_up();
+                break 'main;
+            }
+
             // Get the depth this thread needs to search to.
             let search_depth = self.info.thread_search_depth();
             
@@ -103,9 +213,9 @@ impl Search {
             let (mut alpha_idx, mut beta_idx) = (0, 0);
             
             loop {
-                let best_score = self.alpha_beta(alpha, beta, true, search_depth, search_depth);
+                let score = self.alpha_beta(alpha, beta, true, search_depth, search_depth);
                 
-                if !self.info.is_searching() {
+                if self.is_stopped() {
                     break 'main;
                 }
                 
@@ -113,19 +223,24 @@ impl Search {
                     break;
                 }
                 
-                if best_score <= alpha {
+                if score <= alpha {
                     alpha_idx = MAX_IDX.min(alpha_idx + 1);
-                    alpha = best_score - params::ASPIRATION_WINDOW[alpha_idx];
-                } else if best_score >= beta {
+                    alpha = score - params::ASPIRATION_WINDOW[alpha_idx];
+                } else if score >= beta {
                     beta_idx = MAX_IDX.min(beta_idx + 1);
-                    beta = best_score + params::ASPIRATION_WINDOW[beta_idx];
+                    beta = score + params::ASPIRATION_WINDOW[beta_idx];
                 } else {
+                    best_score = score;
                     break;
                 }
             }
+
+            // Account for the nodes searched during that iteration.
+            self.info.add_stats(&self.stats);
+            self.stats = SearchStats::default();
             
             if let Some(mv) = self.best_move {
-                self.info.report_move(mv, search_depth);
+                self.info.report_move(mv, best_score, search_depth);
             }
         }
     }
@@ -135,9 +250,11 @@ impl Search {
         if depth == 0 {
             return self.quiescence(alpha, beta);
         }
+
+        self.count_node();
         
-        if utils::is_pseudo_draw(&self.board, alpha, self.depth == 0) {
-            alpha = utils::prng_draw_value(&mut self.seed);
+        if utils::is_pseudo_draw(&self.board, alpha, self.draw_score(), self.depth == 0) {
+            alpha = self.draw_score() + utils::prng_draw_value(&mut self.seed);
             if alpha >= beta {
                 return alpha;
             }
@@ -147,11 +264,12 @@ impl Search {
             return self.eval.get(self.board.get_side_to_move());
         }
         
-        if let Some((mv, score)) = self.info.get_table().probe(self.board.get_zobrist(), alpha, beta, depth) {
-            if self.board.is_pseudo_legal(mv) && self.board.is_legal(mv) {
+        if let Some((mv, score)) = self.info.get_table().probe(self.board.get_zobrist(), alpha, beta, depth, self.depth) {
+            if self.board.is_pseudo_legal(mv) && self.board.is_legal(mv) && self.is_root_move(mv) {
                 if score >= alpha && self.depth == 0 {
                     self.best_move = Some(mv);
                 }
+                self.stats.tt_hits += 1;
                 return score;
             }
         }
@@ -179,19 +297,50 @@ impl Search {
         let mut best_move = None;
         let mut picker = MovePicker::new(&self.board, &self.buffer);
         let mut move_count = 0;
+
+        // At the root, the best move of the previous iteration is tried first.
+        let first_move = self.best_move.filter(|&mv| self.depth == 0 && self.board.is_pseudo_legal(mv));
+        let mut first = first_move;
     
-        while let Some(mv) = picker.next(&self.board, &self.heuristics, self.depth, &mut self.buffer) {
-            if !self.board.is_legal(mv) {
+        loop {
+            let mv = match first.take() {
+                Some(mv) => mv,
+                None => match picker.next(&self.board, &self.heuristics, self.depth, &mut self.buffer) {
+                    Some(mv) if Some(mv) == first_move => continue,
+                    Some(mv) => mv,
+                    None => break,
+                },
+            };
+
+            if !self.board.is_legal(mv) || !self.is_root_move(mv) {
                 continue;
             }
 
             self.depth += 1;
             self.eval.do_move(&mut self.board, mv);
-            let score = -self.alpha_beta(-beta, -alpha, do_null, depth-1, search_depth);
+
+            // Late quiet moves are searched at a reduced depth first, and only searched again
+            // at full depth if they turn out to be better than expected. Captures, promotions,
+            // check evasions and checks are never reduced.
+            let reduction = if !in_check && alpha.is_finite() && !mv.is_capture() && !mv.is_promote() && !mv.is_en_passant() && self.board.get_checkers().empty() {
+                params::late_move_reduction(depth, move_count)
+            } else {
+                0
+            };
+
+            // Principal variation search: the first move is searched with the full window, the
+            // others only have to be proven not better than it. Without a finite alpha, there is
+            // no bound to prove.
+            let score = if move_count == 0 || !alpha.is_finite() {
+                -self.alpha_beta(-beta, -alpha, do_null, depth - 1, search_depth)
+            } else {
+                self.search_null_window(alpha, beta, do_null, depth, reduction, search_depth)
+            };
+
             self.eval.undo_move(&mut self.board, mv);
             self.depth -= 1;
 
-            if self.info.search_depth() >= search_depth || !self.info.is_searching() {
+            if self.info.search_depth() >= search_depth || self.is_stopped() {
                 picker.truncate(&mut self.buffer);
                 return 0.0;
             }
@@ -204,14 +353,16 @@ impl Search {
                     if score >= beta {
                         if !mv.is_capture() {
                             self.heuristics.store_killer(mv, self.depth);
+                            self.heuristics.update_history(mv, depth);
                         }
 
-                        self.info.get_table().insert(TableEntry::new(
+                        self.store(TableEntry::new(
                             &self.board,
                             mv, 
                             beta,
                             depth, 
-                            TableEntryFlag::Beta
+                            TableEntryFlag::Beta,
+                            self.depth,
                         ));
                         
                         picker.truncate(&mut self.buffer);
@@ -219,7 +370,7 @@ impl Search {
                     }
 
                     if !mv.is_capture() {
-                        self.heuristics.update_history(mv, self.depth);
+                        self.heuristics.update_history(mv, depth);
                     }
     
                     alpha = score;
@@ -233,41 +384,68 @@ impl Search {
         
         if move_count == 0 {
             return if in_check {
-                -eval::value_of(Piece::King) + self.depth as f32
+                -eval::MATE + self.depth as f32
             } else {
-                0.0
+                self.draw_score()
             };
         }
         
         if alpha != old_alpha {
-            self.info.get_table().insert(TableEntry::new(
+            self.store(TableEntry::new(
                 &self.board,
                 best_move.unwrap(), 
                 best_score, 
                 depth, 
-                TableEntryFlag::Exact
+                TableEntryFlag::Exact,
+                self.depth,
             ));
             
             if self.depth == 0 {
                 self.best_move = best_move;
             }
         } else {
-            self.info.get_table().insert(TableEntry::new(
+            self.store(TableEntry::new(
                 &self.board,
                 best_move.unwrap(), 
                 best_score, 
                 depth, 
-                TableEntryFlag::Alpha
+                TableEntryFlag::Alpha,
+                self.depth,
             ));
         }
         
         alpha
     }
 
-    /// Return the value of the position, computed with a quiescent search (only considering captures).
+    /// Searches the move that was just played, which is not the first one of the node, and returns
+    /// its score for the side that played it. It is searched with a null window around alpha, which
+    /// only proves it is not better than the moves before, and is much cheaper. If it fails high,
+    /// it is searched again: at full depth if it was reduced, then with the full window to get its
+    /// exact score, unless the node itself is searched with a null window.
+    fn search_null_window(&mut self, alpha: f32, beta: f32, do_null: bool, depth: u8, reduction: u8, search_depth: u8) -> f32 {
+        let mut score = -self.alpha_beta(-alpha - 0.01, -alpha, do_null, depth - 1 - reduction, search_depth);
+
+        // The re-search of a reduced move, at full depth.
+        if score > alpha && reduction > 0 {
+            score = -self.alpha_beta(-alpha - 0.01, -alpha, do_null, depth - 1, search_depth);
+        }
+
+        // The re-search of a move failing high inside the principal variation, with the full window.
+        if score > alpha && score < beta {
+            score = -self.alpha_beta(-beta, -alpha, do_null, depth - 1, search_depth);
+        }
+
+        score
+    }
+
+    /// Return the value of the position, computed with a quiescent search (only considering captures
+    /// and queen promotions, skipping those losing material according to the exchange evaluation).
     fn quiescence(&mut self, mut alpha: f32, beta: f32) -> f32 {
-        if utils::is_pseudo_draw(&self.board, alpha, self.depth == 0) {
-            alpha = utils::prng_draw_value(&mut self.seed);
+        self.count_node();
+        self.stats.qnodes += 1;
+
+        if utils::is_pseudo_draw(&self.board, alpha, self.draw_score(), self.depth == 0) {
+            alpha = self.draw_score() + utils::prng_draw_value(&mut self.seed);
             if alpha >= beta {
                 return alpha;
             }
@@ -294,10 +472,17 @@ impl Search {
     
         alpha = alpha.max(stand_pat);
     
+        let in_check = self.board.get_checkers().not_empty();
         let mut captures = Captures::new(&self.board, &mut self.buffer);
     
         while let Some(mv) = captures.next(&mut self.buffer) {
-            if eval::value_of(mv.get_capture()) + params::DELTA < alpha || !self.board.is_legal(mv) {
+            // Promotions are never pruned by delta, as they gain much more than their capture.
+            if !mv.is_promote() && eval::value_of(mv.get_capture()) + params::DELTA < alpha || !self.board.is_legal(mv) {
+                continue;
+            }
+
+            // Captures losing material are not worth searching, unless escaping a check.
+            if !in_check && self.board.see(mv) < 0 {
                 continue;
             }
     
@@ -307,7 +492,7 @@ impl Search {
             self.eval.undo_move(&mut self.board, mv);
             self.depth -= 1;
     
-            if !self.info.is_searching() {
+            if self.is_stopped() {
                 captures.truncate(&mut self.buffer);
                 return 0.0;
             }
@@ -326,3 +511,218 @@ impl Search {
         alpha
     }
 }
+
+//#################################################################################################
+//
+//                                        fn fixed_depth()
+//
+//#################################################################################################
+
+/// Searches the board of the standalone info to the given depth on the current thread, adding
+/// the statistics of the search to the given ones. Returns the best move and its score, or
+/// None if there are no legal moves.
+pub(crate) fn fixed_depth(info: GlobalInfo, net: &Arc<Net>, depth: u8, seed: u32, stats: &mut SearchStats) -> Option<(Move, f32)> {
+    let board = info.board();
+    let info = Arc::new(info);
+    let start = utils::Clock::start();
+
+    let mut search = Search::new(seed, info, net.clone());
+    search.set_board(board);
+    let res = search.search_depth(depth.max(1));
+
+    stats.nodes += search.stats.nodes;
+    stats.qnodes += search.stats.qnodes;
+    stats.tt_hits += search.stats.tt_hits;
+    stats.elapsed += start.elapsed();
+
+    res
+}
+
+//#################################################################################################
+//
+//                                        fn iterative()
+//
+//#################################################################################################
+
+/// Searches the board of the standalone info to the depths 1 to max_depth in turn on the current
+/// thread, calling the callback after each iteration. Returns the principal variation of the
+/// last iteration, empty if there are no legal moves.
+pub(crate) fn iterative(info: GlobalInfo, net: &Arc<Net>, max_depth: u8, seed: u32, mut callback: impl FnMut(&IterationInfo)) -> Vec<Move> {
+    let board = &info.board();
+    let info = Arc::new(info);
+    let start = utils::Clock::start();
+
+    let mut search = Search::new(seed, info.clone(), net.clone());
+    search.set_board(board.clone());
+
+    let mut pv = Vec::new();
+
+    for depth in 1..=max_depth.max(1) {
+        let (mv, score) = match search.search_depth(depth) {
+            Some(result) => result,
+            None => break,
+        };
+
+        search.stats.elapsed = start.elapsed();
+        pv = info.get_table().principal_variation(board, mv);
+
+        callback(&IterationInfo {
+            depth,
+            score,
+            stats: search.stats,
+            pv: pv.clone(),
+            hashfull: info.get_table().hashfull(),
+        });
+    }
+
+    pv
+}
+
+//#################################################################################################
+//
+//                                      fn parallel_root()
+//
+//#################################################################################################
+
+/// Scores every legal move of the board of the standalone info, or only its root moves if any
+/// of them is legal, by searching them to the given depth, the moves being split between the
+/// given number of threads. Returns the moves with their scores, from the best to the worst.
+pub(crate) fn parallel_root(info: GlobalInfo, net: &Arc<Net>, depth: u8, threads: usize, mut seed: u32) -> Vec<(Move, f32)> {
+    let board = &info.board();
+    let mut moves = Vec::new();
+    movegen::legals(board, &mut moves);
+
+    let root_moves = info.root_moves();
+    if moves.iter().any(|mv| root_moves.contains(mv)) {
+        moves.retain(|mv| root_moves.contains(mv));
+    }
+
+    let info = Arc::new(info);
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(moves.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let mut search = Search::new(utils::xorshift32(&mut seed), info.clone(), net.clone());
+            let (moves, next, results) = (&moves, &next, &results);
+
+            scope.spawn(move || {
+                search.set_board(board.clone());
+
+                // Take the next root move that was not searched yet.
+                while let Some(&mv) = moves.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let score = search.search_root_move(mv, depth.max(1));
+                    results.lock().unwrap().push((mv, score));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by(|(_, score1), (_, score2)| score2.partial_cmp(score1).unwrap());
+    results
+}
+
+//#################################################################################################
+//
+//                                        fn weak_move()
+//
+//#################################################################################################
+
+/// Returns a move for a weakened engine of the given strength (at most MAX_STRENGTH). The root
+/// moves are searched shallowly, deeper with higher strengths, and with a probability
+/// growing as the strength decreases a random move is picked among those whose score is within
+/// a margin of the best one. That margin tightens with the strength: at the maximum strength the best
+/// move is always played, at strength 0 any legal move may be. Returns None if there are no legal moves.
+pub(crate) fn weak_move(info: GlobalInfo, net: &Arc<Net>, strength: u8, seed: &mut u32) -> Option<Move> {
+    let strength = strength.min(params::MAX_STRENGTH);
+    let weakness = params::MAX_STRENGTH - strength;
+
+    let scored = parallel_root(info, net, 1 + strength / 3, 1, utils::xorshift32(seed));
+    let &(best_move, best_score) = scored.first()?;
+
+    // Play the best move, unless we are to blunder.
+    if utils::xorshift32(seed) % u32::from(params::MAX_STRENGTH) >= u32::from(weakness) {
+        return Some(best_move);
+    }
+
+    let margin = if strength == 0 {
+        f32::INFINITY
+    } else {
+        params::WEAK_MARGIN * f32::from(weakness)
+    };
+
+    // The moves are sorted from best to worst, pick one within the margin.
+    let candidates = scored.iter().take_while(|(_, score)| best_score - score <= margin).count();
+    Some(scored[utils::xorshift32(seed) as usize % candidates].0)
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chess::board::{Board, STARTPOS_FEN};
+
+    use crate::engine::GlobalInfo;
+    use crate::eval::Net;
+    use crate::table::TranspositionTable;
+    use crate::utils;
+
+    use super::Search;
+
+    /// The path to the network shipped with the engine.
+    const NET_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/nets/nnue.bin");
+
+    /// Returns a search of the given position, flagged as searching.
+    fn search(fen: &str) -> Search {
+        chess::init();
+
+        let net = Net::from_bytes(&std::fs::read(NET_PATH).unwrap()).unwrap();
+        let board = Board::new(fen).unwrap();
+        let info = GlobalInfo::new(board.clone(), 1, TranspositionTable::with_size_mb(1)).standalone();
+
+        let mut search = Search::new(1, Arc::new(info), net);
+        search.set_board(board);
+        search
+    }
+
+    #[test]
+    fn quiescence_promotion() {
+        // Already a rook ahead, promoting still wins a queen.
+        let mut search = search("6k1/P4ppp/8/8/8/8/5PPP/2R3K1 w - - 0 1");
+        let stand_pat = search.eval.get(search.board.get_side_to_move());
+
+        assert!(search.quiescence(f32::NEG_INFINITY, f32::INFINITY) > stand_pat + 5.0);
+    }
+
+    #[test]
+    fn contempt() {
+        // Without contempt, an upcoming repetition is scored as an even position.
+        let mut shuffle = search(STARTPOS_FEN);
+        for mv in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3"] {
+            let mv = shuffle.board.parse_move(mv).unwrap();
+            shuffle.board.do_move(mv);
+        }
+        assert!(utils::is_pseudo_draw(&shuffle.board, f32::NEG_INFINITY, shuffle.draw_score(), false));
+        assert_eq!(shuffle.draw_score(), 0.0);
+
+        // The contempt is against white, to move at the root: its sign flips with the side to move.
+        shuffle.contempt = 0.5;
+        assert_eq!(shuffle.draw_score(), 0.5);
+        let mv = shuffle.board.parse_move("g8f6").unwrap();
+        shuffle.board.do_move(mv);
+        assert_eq!(shuffle.draw_score(), -0.5);
+
+        // Black is stalemated at the root.
+        let mut stalemate = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(stalemate.alpha_beta(f32::NEG_INFINITY, f32::INFINITY, false, 1, 1), 0.0);
+        stalemate.contempt = 0.5;
+        assert_eq!(stalemate.alpha_beta(f32::NEG_INFINITY, f32::INFINITY, false, 1, 1), -0.5);
+    }
+}
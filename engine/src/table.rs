@@ -1,8 +1,12 @@
+use std::collections::HashSet;
+use std::mem;
+use std::sync::atomic::{AtomicPtr, AtomicU8, AtomicUsize, Ordering};
+
 use chess::board::Board;
 use chess::moves::Move;
 use chess::zobrist::Zobrist;
 
-use crate::params;
+use crate::{eval, params};
 
 //#################################################################################################
 //
@@ -27,21 +31,24 @@ pub(crate) struct TableEntry {
     pub(crate) score: f32,
     depth: u8,
     flag: TableEntryFlag,
+    generation: u8,
 }
 
 // ================================ pub(crate) impl
 
 impl TableEntry {
-    /// Creates a new table entry based with the given values.
+    /// Creates a new table entry based with the given values. The score is relative to the
+    /// root, which is ply plies away: mate scores are stored relative to the node instead.
     #[inline]
-    pub(crate) fn new(board: &Board, mv: Move, score: f32, depth: u8, flag: TableEntryFlag) -> TableEntry {
+    pub(crate) fn new(board: &Board, mv: Move, score: f32, depth: u8, flag: TableEntryFlag, ply: u8) -> TableEntry {
         TableEntry {
             zobrist: board.get_zobrist(), 
             age: board.get_ply(), 
             mv, 
-            score, 
+            score: eval::score_to_node(score, ply), 
             depth,
             flag,
+            generation: 0,
         }
     }
 }
@@ -55,36 +62,99 @@ impl TableEntry {
 /// The type of a bucket in the map.
 type Bucket = Option<TableEntry>;
 
-/// The size in buckets of the table. It is a power of two for
-/// faster indexing.
-const NUM_BUCKETS: usize = (params::TABLE_SIZE / std::mem::size_of::<Bucket>()).next_power_of_two();
-
 /// The struct representing an access to a transposition table.
 /// A transposition table is a lock-less memory-efficient concurrent hashmap.
 /// It's only default is that it is lossy and may rarely corrupt some of it's data.
-#[repr(transparent)]
-#[derive(Clone, Debug)]
-pub(crate) struct TranspositionTable(*mut Bucket);
+/// The buckets are a leaked vector, whose size in buckets is a power of two for
+/// faster indexing: the mask is that size minus one.
+#[derive(Debug)]
+pub(crate) struct TranspositionTable {
+    buckets: AtomicPtr<Bucket>,
+    mask: AtomicUsize,
+    generation: AtomicU8,
+}
 
 // ================================ pub(crate) impl
 
 impl TranspositionTable {
-    /// Creates a new transposition table, from leaking a vector.
+    /// Creates a new transposition table of the default size, params::TABLE_SIZE.
     pub(crate) fn new() -> TranspositionTable {
-        let mut vec = vec![None; NUM_BUCKETS];
-        let ptr = vec.as_mut_ptr();
-        vec.leak();
+        TranspositionTable::with_size_mb(params::TABLE_SIZE >> 20)
+    }
+
+    /// Creates a new transposition table of at most the given size in megabytes, the number
+    /// of buckets being rounded down to a power of two. It has at least one bucket.
+    pub(crate) fn with_size_mb(mb: usize) -> TranspositionTable {
+        let (buckets, mask) = TranspositionTable::alloc(mb);
+
+        TranspositionTable {
+            buckets: AtomicPtr::new(buckets),
+            mask: AtomicUsize::new(mask),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    /// Replaces the table with an empty one of at most the given size in megabytes,
+    /// like with_size_mb().
+    ///
+    /// # Safety
+    ///
+    /// No other thread may access the table while it is resized.
+    pub(crate) unsafe fn resize_mb(&self, mb: usize) {
+        let (buckets, mask) = TranspositionTable::alloc(mb);
+
+        let old_buckets = self.buckets.swap(buckets, Ordering::AcqRel);
+        let old_mask = self.mask.swap(mask, Ordering::AcqRel);
+        TranspositionTable::free(old_buckets, old_mask);
+    }
+
+    /// Empties the table, resetting all of it's buckets. Should be done between unrelated
+    /// positions, a new game for example. Threads searching at the same time may corrupt entries.
+    pub(crate) fn clear(&self) {
+        let buckets = self.buckets.load(Ordering::Relaxed);
+
+        for i in 0..=self.mask.load(Ordering::Relaxed) {
+            // SAFE: not inherently unsafe, at worst we risk corrupting an entry.
+            unsafe {*buckets.add(i) = None};
+        }
+    }
+
+    /// Starts a new generation of entries, to be done before each search. Entries of previous
+    /// generations are still probed, but are always replaced: a cheap alternative to clear().
+    #[inline]
+    pub(crate) fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the occupancy of the table in per mille, as reported by uci's "hashfull": the number
+    /// of entries of the current generation among the first thousand buckets.
+    pub(crate) fn hashfull(&self) -> u16 {
+        let buckets = self.buckets.load(Ordering::Relaxed);
+        let generation = self.generation.load(Ordering::Relaxed);
+        let samples = (self.mask.load(Ordering::Relaxed) + 1).min(1000);
+
+        let full = (0..samples).filter(|&i| {
+            // SAFE: not inherently unsafe, at worst we risk getting a currupted entry.
+            matches!(unsafe {*buckets.add(i)}, Some(entry) if entry.generation == generation)
+        }).count();
 
-        TranspositionTable(ptr)
+        (full * 1000 / samples) as u16
+    }
+
+    /// Returns the size of the table in megabytes, rounded up: as the number of buckets was
+    /// rounded down, a table created with that size has as many buckets as this one.
+    pub(crate) fn size_mb(&self) -> usize {
+        ((self.mask.load(Ordering::Relaxed) + 1) * mem::size_of::<Bucket>() + (1 << 20) - 1) >> 20
     }
     
     /// Inserts into the hashtable, or not depending on the replacement strategy.
     #[inline]
-    pub(crate) fn insert(&self, entry: TableEntry) {
-        let i = entry.zobrist.idx::<NUM_BUCKETS>();
+    pub(crate) fn insert(&self, mut entry: TableEntry) {
+        let bucket = self.bucket(entry.zobrist);
+        entry.generation = self.generation.load(Ordering::Relaxed);
 
         // SAFE: not inherently unsafe, at worst we risk getting a currupted entry.
-        if let Some(prev) = unsafe {*self.0.offset(i)} {
+        if let Some(prev) = unsafe {*bucket}.filter(|prev| prev.generation == entry.generation) {
             let replace_score = 
                 entry.depth as i32 - prev.depth as i32 + 
                 entry.age   as i32 - prev.age   as i32 +
@@ -96,19 +166,54 @@ impl TranspositionTable {
         }
 
         // SAFE: not inherently unsafe, at worst we risk corrupting an entry.
-        unsafe {*self.0.offset(i) = Some(entry)};
+        unsafe {*bucket = Some(entry)};
     }
 
-    /// Probes the hashmap and gets any pertinent information available.
+    /// Returns the move stored for that position, whatever the bounds of it's score.
     #[inline]
-    pub(crate) fn probe(&self, zobrist: Zobrist, alpha: f32, beta: f32, depth: u8) -> Option<(Move, f32)> {
-        let i = zobrist.idx::<NUM_BUCKETS>();
-        
+    pub(crate) fn get_move(&self, zobrist: Zobrist) -> Option<Move> {
         // SAFE: not inherently unsafe, at worst we risk getting a currupted entry.
-        if let Some(entry) = unsafe {*self.0.offset(i)} {
+        match unsafe {*self.bucket(zobrist)} {
+            Some(entry) if entry.zobrist == zobrist => Some(entry.mv),
+            _ => None,
+        }
+    }
+
+    /// Returns the principal variation starting with the given move, by following the moves
+    /// stored in the table from the board. It stops at the first move that is missing or
+    /// illegal, a collision may have replaced it, or when a position repeats, so as not
+    /// to cycle forever. It is at most MAX_DEPTH moves long.
+    pub(crate) fn principal_variation(&self, board: &Board, mv: Move) -> Vec<Move> {
+        let mut board = board.clone();
+        let mut seen = HashSet::new();
+        let mut pv = Vec::new();
+        let mut next = Some(mv);
+
+        while let Some(mv) = next {
+            if pv.len() == params::MAX_DEPTH || !seen.insert(board.get_zobrist().get_raw()) {
+                break;
+            }
+            if !board.is_pseudo_legal(mv) || !board.is_legal(mv) {
+                break;
+            }
+
+            board.do_move(mv);
+            pv.push(mv);
+            next = self.get_move(board.get_zobrist());
+        }
+
+        pv
+    }
+
+    /// Probes the hashmap and gets any pertinent information available. The score returned
+    /// is relative to the root, which is ply plies away.
+    #[inline]
+    pub(crate) fn probe(&self, zobrist: Zobrist, alpha: f32, beta: f32, depth: u8, ply: u8) -> Option<(Move, f32)> {
+        // SAFE: not inherently unsafe, at worst we risk getting a currupted entry.
+        if let Some(entry) = unsafe {*self.bucket(zobrist)} {
             if entry.zobrist == zobrist && entry.depth >= depth {
                 let mv = entry.mv;
-                let score = entry.score;
+                let score = eval::score_from_node(entry.score, ply);
 
                 return match entry.flag {
                     TableEntryFlag::Exact => Some((mv, score)),
@@ -123,17 +228,161 @@ impl TranspositionTable {
     }
 }
 
+// ================================ impl
+
+impl TranspositionTable {
+    /// Allocates the buckets of a table of at most the given size in megabytes, leaking them.
+    /// Returns the pointer to the buckets and the mask.
+    fn alloc(mb: usize) -> (*mut Bucket, usize) {
+        let num_buckets = ((mb << 20) / mem::size_of::<Bucket>()).max(1);
+
+        // Round down to a power of two.
+        let num_buckets = 1 << (usize::BITS - 1 - num_buckets.leading_zeros());
+
+        let mut vec = vec![None; num_buckets];
+        let ptr = vec.as_mut_ptr();
+        mem::forget(vec);
+
+        (ptr, num_buckets - 1)
+    }
+
+    /// Frees buckets allocated by alloc(), with the mask it returned.
+    ///
+    /// # Safety
+    ///
+    /// The buckets must not be used afterwards.
+    unsafe fn free(buckets: *mut Bucket, mask: usize) {
+        drop(Vec::from_raw_parts(buckets, mask + 1, mask + 1));
+    }
+
+    /// Returns a pointer to the bucket of that zobrist.
+    #[inline]
+    fn bucket(&self, zobrist: Zobrist) -> *mut Bucket {
+        let i = zobrist.get_raw() as usize & self.mask.load(Ordering::Relaxed);
+
+        // SAFE: the mask keeps the index in the vector.
+        unsafe {self.buckets.load(Ordering::Relaxed).add(i)}
+    }
+}
+
 // ================================ traits impl
 
 impl Drop for TranspositionTable {
     /// TranspositionTable needs to be manually dropped.
     fn drop(&mut self) {
-        // SAFE: the pointer is dropped only once
-        unsafe {Box::from_raw(self.0)};
+        // SAFE: the buckets were allocated by alloc(), and are freed only once.
+        unsafe {TranspositionTable::free(*self.buckets.get_mut(), *self.mask.get_mut())};
     }
 }
 
 // rustc correctly assesses that our TranspositionTable is not thread-safe.
 // Let us turn a blind eye to that.
 unsafe impl Send for TranspositionTable {}
-unsafe impl Sync for TranspositionTable {}
\ No newline at end of file
+unsafe impl Sync for TranspositionTable {}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+    use std::sync::atomic::Ordering;
+
+    use chess::board::Board;
+
+    use super::{Bucket, TableEntry, TableEntryFlag, TranspositionTable};
+
+    /// Returns the number of buckets of the table.
+    fn num_buckets(table: &TranspositionTable) -> usize {
+        table.mask.load(Ordering::Relaxed) + 1
+    }
+
+    /// Returns the starting position.
+    fn startpos() -> Board {
+        chess::init();
+        Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap()
+    }
+
+    #[test]
+    fn sizes() {
+        // At least one bucket.
+        assert_eq!(num_buckets(&TranspositionTable::with_size_mb(0)), 1);
+
+        for &mb in &[1, 3, 4, 32, 100] {
+            let table = TranspositionTable::with_size_mb(mb);
+
+            // Rounded down to a power of two.
+            assert!(num_buckets(&table).is_power_of_two());
+            assert!(num_buckets(&table) * mem::size_of::<Bucket>() <= mb << 20);
+            assert!(num_buckets(&table) * 2 * mem::size_of::<Bucket>() > mb << 20);
+
+            // The size reported gives back the same table.
+            assert!(table.size_mb() <= mb);
+            assert_eq!(num_buckets(&TranspositionTable::with_size_mb(table.size_mb())), num_buckets(&table));
+
+            // Resizing is like creating a new table.
+            let resized = TranspositionTable::with_size_mb(1);
+            // SAFE: no other thread accesses the table.
+            unsafe {resized.resize_mb(mb)};
+            assert_eq!(num_buckets(&resized), num_buckets(&table));
+            assert_eq!(resized.size_mb(), table.size_mb());
+        }
+    }
+
+    #[test]
+    fn clear() {
+        let board = startpos();
+        let mv = board.parse_move("e2e4").unwrap();
+        let table = TranspositionTable::with_size_mb(1);
+
+        table.insert(TableEntry::new(&board, mv, 0.5, 4, TableEntryFlag::Exact, 0));
+        assert_eq!(table.probe(board.get_zobrist(), -1.0, 1.0, 4, 0), Some((mv, 0.5)));
+
+        table.clear();
+        assert_eq!(table.probe(board.get_zobrist(), -1.0, 1.0, 0, 0), None);
+        assert_eq!(table.get_move(board.get_zobrist()), None);
+    }
+
+    #[test]
+    fn generations() {
+        let board = startpos();
+        let (deep, shallow) = (board.parse_move("e2e4").unwrap(), board.parse_move("d2d4").unwrap());
+        let table = TranspositionTable::with_size_mb(1);
+
+        // A shallower entry of the same search does not replace a deeper one.
+        table.insert(TableEntry::new(&board, deep, 0.0, 8, TableEntryFlag::Exact, 0));
+        table.insert(TableEntry::new(&board, shallow, 0.0, 1, TableEntryFlag::Exact, 0));
+        assert_eq!(table.get_move(board.get_zobrist()), Some(deep));
+
+        // Entries of the previous searches are always replaced.
+        table.new_search();
+        table.insert(TableEntry::new(&board, shallow, 0.0, 1, TableEntryFlag::Exact, 0));
+        assert_eq!(table.get_move(board.get_zobrist()), Some(shallow));
+    }
+
+    #[test]
+    fn hashfull() {
+        let board = startpos();
+        let mv = board.parse_move("e2e4").unwrap();
+
+        // The only bucket is sampled: either empty, or full with an entry of the current search.
+        let table = TranspositionTable::with_size_mb(0);
+        assert_eq!(table.hashfull(), 0);
+        table.insert(TableEntry::new(&board, mv, 0.0, 1, TableEntryFlag::Exact, 0));
+        assert_eq!(table.hashfull(), 1000);
+        table.new_search();
+        assert_eq!(table.hashfull(), 0);
+
+        // In per mille of the first thousand buckets, a quarter of them being full.
+        let table = TranspositionTable::with_size_mb(1);
+        let buckets = table.buckets.load(Ordering::Relaxed);
+        for i in (0..num_buckets(&table)).step_by(4) {
+            // SAFE: the index is in the table, and no other thread accesses it.
+            unsafe {*buckets.add(i) = Some(TableEntry::new(&board, mv, 0.0, 1, TableEntryFlag::Exact, 0))};
+        }
+        assert_eq!(table.hashfull(), 250);
+    }
+}
@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use crate::params;
+
+//#################################################################################################
+//
+//                                       struct TimeManager
+//
+//#################################################################################################
+
+/// Budgets the time of a search. The search should not start a new iteration after
+/// the soft deadline, and must stop as soon as possible after the hard one.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeManager {
+    start: Instant,
+    soft: Duration,
+    hard: Duration,
+}
+
+// ================================ pub impl
+
+impl TimeManager {
+    /// Budgets the time for a move, from the time remaining on the clock, the increment and
+    /// the number of moves to play before the next time control, if any. The ply of the game
+    /// is used to be more generous in the opening. The search starts now.
+    pub fn new(time: Duration, inc: Duration, movestogo: Option<u32>, ply: u16) -> TimeManager {
+        // Never risk losing on time because of the communication with the gui.
+        let available = time.saturating_sub(params::MOVE_OVERHEAD);
+
+        let moves_to_go = movestogo.unwrap_or(params::MOVES_TO_GO).max(1);
+        let mut soft = available / moves_to_go + inc * 3 / 4;
+
+        // Up to half more time in the opening, decreasing until the middle game.
+        let moves_played = u32::from(ply / 2).min(params::OPENING_MOVES);
+        soft += soft * (params::OPENING_MOVES - moves_played) / (2 * params::OPENING_MOVES);
+
+        // Under time pressure, the budget shrinks with the time left, down to half of it.
+        if available < params::TIME_PRESSURE {
+            soft = soft / 2 + soft.mul_f64(available.as_secs_f64() / params::TIME_PRESSURE.as_secs_f64()) / 2;
+        }
+
+        let hard = (soft * params::HARD_TIME_RATIO).min(available * 3 / 4);
+
+        TimeManager {
+            start: Instant::now(),
+            soft: soft.min(hard),
+            hard,
+        }
+    }
+
+    /// Budgets exactly the given time for a move, like with uci's "go movetime".
+    /// The search starts now.
+    pub fn fixed(time: Duration) -> TimeManager {
+        TimeManager {
+            start: Instant::now(),
+            soft: time,
+            hard: time,
+        }
+    }
+
+    /// Returns the time after which no new iteration should be started, from the start of the search.
+    pub fn soft_limit(&self) -> Duration {
+        self.soft
+    }
+
+    /// Returns the time after which the search must stop, from the start of the search.
+    pub fn hard_limit(&self) -> Duration {
+        self.hard
+    }
+
+    /// Returns the time elapsed since the start of the search.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Returns true if the hard deadline is passed, and the search must stop now.
+    #[inline]
+    pub fn should_stop(&self) -> bool {
+        self.elapsed() >= self.hard
+    }
+
+    /// Returns true if a new iteration may be started: the soft deadline is not passed,
+    /// and the iteration is expected to end before the hard one. As each iteration takes
+    /// about as long as all the previous ones, it is expected to end after twice the
+    /// time elapsed so far.
+    #[inline]
+    pub fn can_start_iteration(&self) -> bool {
+        let elapsed = self.elapsed();
+        elapsed < self.soft && elapsed * 2 < self.hard
+    }
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TimeManager;
+
+    #[test]
+    fn allocation() {
+        let minute = Duration::from_secs(60);
+
+        // The budget is within the time left, and the soft limit before the hard one.
+        for &(time, movestogo) in &[(minute, None), (minute, Some(1)), (Duration::from_millis(100), None)] {
+            let tm = TimeManager::new(time, Duration::ZERO, movestogo, 40);
+            assert!(tm.soft_limit() <= tm.hard_limit());
+            assert!(tm.hard_limit() < time);
+        }
+
+        // More generous in the opening, tighter under time pressure.
+        let opening = TimeManager::new(minute, Duration::ZERO, None, 0);
+        let middle_game = TimeManager::new(minute, Duration::ZERO, None, 60);
+        assert!(opening.soft_limit() > middle_game.soft_limit());
+
+        let pressure = TimeManager::new(Duration::from_secs(3), Duration::ZERO, None, 60);
+        assert!(pressure.soft_limit() < Duration::from_secs(3) / 30);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::eval;
+
+/// Converts an internal score, in pawns and from the point of view of the side to move,
+/// to its uci representation: either "cp <centipawns>" or "mate <moves>", where a
+/// negative number of moves means the side to move is getting mated.
+pub fn format_score(score: f32) -> String {
+    // Mate scores are encoded as eval::MATE minus the number of plies to mate.
+    if eval::is_mate(score) {
+        let plies = eval::MATE - score.abs();
+        let moves = (plies.round() as i32 + 1) / 2;
+        format!("mate {}", if score > 0.0 {moves} else {-moves})
+    } else {
+        format!("cp {}", (score * 100.0).round() as i32)
+    }
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::format_score;
+
+    /// The score of a position where the side to move is mated in that many plies.
+    fn mated_in(plies: u8) -> f32 {
+        -crate::eval::value_of(chess::piece::Piece::King) + f32::from(plies)
+    }
+
+    #[test]
+    fn centipawns() {
+        assert_eq!(format_score(0.0), "cp 0");
+        assert_eq!(format_score(1.234), "cp 123");
+        assert_eq!(format_score(-0.5), "cp -50");
+    }
+
+    #[test]
+    fn mate_in_one() {
+        assert_eq!(format_score(-mated_in(1)), "mate 1");
+    }
+
+    #[test]
+    fn mate_in_three() {
+        assert_eq!(format_score(-mated_in(5)), "mate 3");
+    }
+
+    #[test]
+    fn being_mated() {
+        assert_eq!(format_score(mated_in(0)), "mate 0");
+        assert_eq!(format_score(mated_in(2)), "mate -1");
+        assert_eq!(format_score(mated_in(4)), "mate -2");
+    }
+}
@@ -0,0 +1,416 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Error, Result};
+use clap::{App, Arg};
+
+use chess::board::STARTPOS_FEN;
+use chess::prelude::*;
+use engine::{Engine, IterationInfo, TimeManager};
+
+/// How often the main loop wakes up to check the limits of the search, when no command comes.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// What the main loop waits for: a line from the gui, or the end of an iteration of the search,
+/// with the number of the search it belongs to.
+enum Event {
+    Line(String),
+    Iteration(u32, IterationInfo),
+    Eof,
+}
+
+/// The limits of the search currently running, given to the "go" command.
+/// Its time is budgeted by the engine's time manager.
+#[derive(Default)]
+struct Limits {
+    depth: Option<u8>,
+}
+
+/// The global state of the uci frontend, answering the gui through out. Searches are numbered,
+/// so that the iterations of a search that was stopped are not mistaken for those of the next one.
+struct State<W: Write> {
+    engine: Engine,
+    events: Receiver<Event>,
+    limits: Option<Limits>,
+    search: Arc<AtomicU32>,
+    out: W,
+}
+
+// ================================ Utils
+
+impl<W: Write> State<W> {
+    /// Creates the state of the frontend, answering through out. Returns it with the sender
+    /// through which the lines of the gui are to be sent.
+    fn new(mut engine: Engine, out: W) -> (State<W>, Sender<Event>) {
+        // Lines and iterations are both sent to the main loop, so that it may stop the search
+        // as soon as asked to, or as soon as it's limits are reached.
+        let (sender, events) = mpsc::channel();
+        let search = Arc::new(AtomicU32::new(0));
+
+        let (iterations, current) = (sender.clone(), search.clone());
+        engine.on_iteration(move |info| {
+            iterations.send(Event::Iteration(current.load(Ordering::Relaxed), info.clone())).ok();
+        });
+
+        let state = State {
+            engine,
+            events,
+            limits: None,
+            search,
+            out,
+        };
+
+        (state, sender)
+    }
+
+    /// Sends a line to the gui.
+    fn send(&mut self, line: impl fmt::Display) {
+        writeln!(self.out, "{}", line).ok();
+    }
+
+    /// Returns the next event, or None if none came for a while during a search.
+    fn next_event(&self) -> Option<Event> {
+        if self.limits.is_none() {
+            return Some(self.events.recv().unwrap_or(Event::Eof));
+        }
+
+        match self.events.recv_timeout(POLL_INTERVAL) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(Event::Eof),
+        }
+    }
+
+    /// Stops the search if it is running and prints the best move found.
+    fn finish(&mut self) {
+        if self.limits.take().is_none() {
+            return;
+        }
+
+        self.engine.stop();
+        match self.engine.poll().get_move() {
+            Some(mv) => self.send(format_args!("bestmove {}", mv)),
+            None => self.send("bestmove 0000"),
+        }
+    }
+
+    /// Stops the search if one of it's limits was reached.
+    fn check_limits(&mut self, depth: Option<u8>) {
+        let reached = match &self.limits {
+            Some(limits) => {
+                self.engine.is_time_up() ||
+                limits.depth.zip(depth).is_some_and(|(max_depth, depth)| depth >= max_depth)
+            },
+            None => false,
+        };
+
+        if reached {
+            self.finish();
+        }
+    }
+}
+
+// ================================ Commands
+
+impl<W: Write> State<W> {
+    /// Answers the events until told to quit or the end of the input, then stops the search.
+    fn run(&mut self) {
+        while self.handle(self.next_event()) {}
+
+        // Don't leave a search running.
+        self.finish();
+    }
+
+    /// Answers the event, or checks the limits of the search if None.
+    /// Returns false if the frontend must quit.
+    fn handle(&mut self, event: Option<Event>) -> bool {
+        match event {
+            Some(Event::Line(line)) => self.command(&line),
+            Some(Event::Iteration(search, info)) => {
+                // Only the iterations of the current search are reported.
+                if search == self.search.load(Ordering::Relaxed) && self.limits.is_some() {
+                    self.send(&info);
+                    self.check_limits(Some(info.depth));
+                }
+                true
+            },
+            Some(Event::Eof) => false,
+            None => {
+                self.check_limits(None);
+                true
+            },
+        }
+    }
+
+    /// Answers a line of the gui. Returns false if the frontend must quit.
+    fn command(&mut self, line: &str) -> bool {
+        let mut args = line.split_ascii_whitespace().map(|s| s.to_string());
+
+        let res = match args.next().as_deref() {
+            Some("uci") => {
+                self.send(format_args!("id name Rush {}", engine::VERSION));
+                self.send("id author Benjamin Lefebvre");
+                self.send("option name Hash type spin default 32 min 1 max 65536");
+                self.send("option name Contempt type spin default 0 min -1000 max 1000");
+                self.send("uciok");
+                Ok(())
+            },
+            Some("isready") => {
+                self.send("readyok");
+                Ok(())
+            },
+            Some("ucinewgame") => {
+                self.finish();
+                self.engine.clear_table();
+                Ok(())
+            },
+            Some("setoption") => {
+                self.finish();
+                self.set_option(&mut args)
+            },
+            Some("position") => {
+                self.finish();
+                self.position(&mut args)
+            },
+            Some("go") => {
+                self.finish();
+                self.go(&mut args);
+                Ok(())
+            },
+            Some("stop") => {
+                self.finish();
+                Ok(())
+            },
+            Some("quit") => return false,
+            Some(unknown) => Err(Error::msg(format!("Unknown command: \"{}\".", unknown))),
+            None => Ok(()),
+        };
+
+        // Guis ignore unknown lines, errors are reported as uci "info string".
+        if let Err(e) = res {
+            self.send(format_args!("info string {}", e));
+        }
+
+        true
+    }
+
+    /// Sets the position, from "startpos" or "fen <fen>", followed by optional "moves <moves>".
+    fn position(&mut self, args: &mut impl Iterator<Item = String>) -> Result<()> {
+        let fen = match args.next().as_deref() {
+            Some("startpos") => STARTPOS_FEN.to_string(),
+            Some("fen") => args.by_ref().take_while(|arg| arg != "moves").collect::<Vec<_>>().join(" "),
+            _ => return Err(Error::msg("Expected \"startpos\" or \"fen\" after \"position\".")),
+        };
+
+        let mut board = Board::from_str(&fen)?;
+        for arg in args.skip_while(|arg| arg == "moves") {
+            let mv = board.parse_move(&arg)?;
+            board.do_move(mv);
+        }
+
+        *self.engine.write_board() = board;
+
+        Ok(())
+    }
+
+    /// Starts searching, until told to stop or until the limits given are reached. The arguments
+    /// that are not supported, such as "ponder", "nodes" or "mate", are ignored, as well as those
+    /// that are malformed: a search is always started, or a best move given.
+    fn go(&mut self, args: &mut impl Iterator<Item = String>) {
+        let mut limits = Limits::default();
+        let (mut time, mut inc, mut movestogo, mut movetime) = (None, 0, None, None);
+        let mut search_moves = Vec::new();
+        let board = self.engine.read_board().clone();
+        let (white, ply) = (board.get_side_to_move() == Color::White, board.get_ply());
+
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            // A missing or malformed value is ignored, the next argument being parsed as usual.
+            // Negative values, sent by some guis once out of time, count as 0.
+            let mut value = || {
+                let value = args.peek().and_then(|value| i64::from_str(value).ok())?;
+                args.next();
+                Some(value.max(0) as u64)
+            };
+
+            match arg.as_str() {
+                "depth" => limits.depth = value().map(|depth| depth.min(u64::from(u8::MAX)) as u8).or(limits.depth),
+                "movetime" => movetime = value().or(movetime),
+                "wtime" if white => time = value().or(time),
+                "btime" if !white => time = value().or(time),
+                "winc" if white => inc = value().unwrap_or(inc),
+                "binc" if !white => inc = value().unwrap_or(inc),
+                "movestogo" => movestogo = value().map(|moves| moves.min(u64::from(u32::MAX)) as u32).or(movestogo),
+                "wtime" | "btime" | "winc" | "binc" | "nodes" | "mate" => drop(value()),
+                // The moves go on until the next argument, that is not a move.
+                "searchmoves" => while let Some(mv) = args.peek().and_then(|arg| board.parse_move(arg).ok()) {
+                    search_moves.push(mv);
+                    args.next();
+                },
+                _ => (),
+            }
+        }
+
+        // Without "searchmoves", the restriction of the previous search is lifted.
+        self.engine.set_root_moves(&search_moves);
+
+        // Without any legal move, there is nothing to search.
+        if movegen::count_legals(&self.engine.read_board()) == 0 {
+            self.send("bestmove 0000");
+            return;
+        }
+
+        // A fixed time for the move takes precedence over the clock.
+        let time = match (movetime, time) {
+            (Some(movetime), _) => Some(TimeManager::fixed(Duration::from_millis(movetime))),
+            (None, Some(time)) => Some(TimeManager::new(Duration::from_millis(time), Duration::from_millis(inc), movestogo, ply)),
+            (None, None) => None,
+        };
+        self.engine.set_time_manager(time);
+
+        self.limits = Some(limits);
+
+        // The previous search is stopped, none of its iterations is to come anymore.
+        self.search.fetch_add(1, Ordering::Relaxed);
+
+        // The engine may have found a book move instead of starting.
+        if !self.engine.start() {
+            self.finish();
+        }
+    }
+
+    /// Sets an option, "setoption name <name> value <value>".
+    fn set_option(&mut self, args: &mut impl Iterator<Item = String>) -> Result<()> {
+        let args: Vec<_> = args.collect();
+        let value = args.iter().skip_while(|arg| *arg != "value").nth(1).ok_or(Error::msg("Missing option value."))?;
+
+        match args.get(1).map(|name| name.to_lowercase()).as_deref() {
+            Some("hash") => self.engine.set_table_size(usize::from_str(value)?),
+            Some("contempt") => self.engine.set_contempt(i32::from_str(value)?),
+            _ => return Err(Error::msg("Unknown option.")),
+        }
+
+        Ok(())
+    }
+}
+
+/// The main function parses the programs arguments, initializes the chess library and
+/// the engine, and then answers the uci commands read from the standard input.
+fn main() -> Result<()> {
+    // Initializes the chess library.
+    chess::init();
+
+    // Get the args to the program.
+    let args = App::new("Rush chess engine UCI")
+        .version(engine::VERSION)
+        .author("Benjamin Lefebvre")
+        .about("An implementation of the UCI protocol for the Rush chess engine, to be used with chess GUIs.")
+        .arg(Arg::with_name("net")
+            .index(1)
+            .value_name("NET")
+            .help("The path to the network file to use for evaluation.")
+            .required(true))
+        .arg(Arg::with_name("book")
+            .short("b")
+            .long("book")
+            .value_name("BOOK")
+            .help("Gives the path to a polyglot book (.bin), that the engine will use whenever it can.")
+            .takes_value(true))
+        .get_matches();
+
+    let engine = Engine::new(Board::from_str(STARTPOS_FEN)?, args.value_of("book"), args.value_of("net").unwrap())?;
+    let (mut state, sender) = State::new(engine, io::stdout());
+
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) => if sender.send(Event::Line(line)).is_err() {return},
+                Err(_) => break,
+            }
+        }
+        sender.send(Event::Eof).ok();
+    });
+
+    // The engine joins it's threads when dropped.
+    state.run();
+
+    Ok(())
+}
+
+//#################################################################################################
+//
+//                                              tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess::board::{Board, STARTPOS_FEN};
+    use engine::Engine;
+
+    use super::State;
+
+    /// The path to the network shipped with the engine.
+    const NET_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/nets/nnue.bin");
+
+    /// Returns a frontend writing into a buffer.
+    fn state() -> State<Vec<u8>> {
+        chess::init();
+
+        let engine = Engine::new(Board::from_str(STARTPOS_FEN).unwrap(), None, NET_PATH).unwrap();
+        State::new(engine, Vec::new()).0
+    }
+
+    /// Answers the line, then the events until the search is over. Returns the output.
+    fn answer(state: &mut State<Vec<u8>>, line: &str) -> String {
+        assert!(state.command(line));
+        while state.limits.is_some() {
+            let event = state.next_event();
+            state.handle(event);
+        }
+
+        String::from_utf8(std::mem::take(&mut state.out)).unwrap()
+    }
+
+    #[test]
+    fn position() {
+        let mut state = state();
+
+        answer(&mut state, "position startpos moves e2e4 e7e5 g1f3");
+        assert_eq!(state.engine.read_board().to_string(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+
+        // An illegal move leaves the board as it was.
+        let output = answer(&mut state, "position startpos moves e2e5");
+        assert!(output.starts_with("info string"));
+        assert_eq!(state.engine.read_board().to_string(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+    }
+
+    #[test]
+    fn go_depth() {
+        let mut state = state();
+
+        answer(&mut state, "position startpos");
+        let output = answer(&mut state, "go depth 2");
+        assert_eq!(output.lines().filter(|line| line.starts_with("bestmove")).count(), 1);
+        assert!(output.lines().any(|line| line.starts_with("info depth")));
+        assert!(output.trim_end().lines().last().unwrap().starts_with("bestmove"));
+    }
+
+    #[test]
+    fn go_malformed() {
+        let mut state = state();
+
+        for line in &["go depth x depth 1", "go wtime -100 btime -100", "go depth 1 movetime", "go searchmoves e2e5 depth 1"] {
+            let output = answer(&mut state, line);
+            assert_eq!(output.lines().filter(|line| line.starts_with("bestmove")).count(), 1, "{}", line);
+        }
+    }
+}
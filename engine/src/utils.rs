@@ -1,12 +1,44 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
 use chess::bitboard::BitBoard;
 use chess::board::Board;
 use chess::color::Color;
 use chess::piece::Piece;
-use chess::square::Square;
+
+/// Measures the time elapsed since it was started. On wasm, where std has no clock,
+/// it always reads zero instead of panicking.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Clock {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+}
+
+impl Clock {
+    /// Starts a clock now.
+    #[inline]
+    pub(crate) fn start() -> Clock {
+        Clock {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns the time elapsed since the clock was started.
+    #[inline]
+    pub(crate) fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.start.elapsed();
+
+        #[cfg(target_arch = "wasm32")]
+        return Duration::ZERO;
+    }
+}
 
 /// Returns a random seed based on the current time.
+/// This is the only non-deterministic source of randomness of the crate, and is
+/// only used when no explicit seed is given.
 #[inline]
 pub(crate) fn seed() -> u32 {
     (SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).expect("Cannot get system time.").as_nanos() & 0xFFFFFFFF) as u32
@@ -22,10 +54,11 @@ pub(crate) fn xorshift32(seed: &mut u32) -> u32 {
 }
 
 /// Returns true if the board is in pseudo-draw because of either the
-/// 50 move rule or an incoming threefold repetition.
+/// 50 move rule or an incoming threefold repetition, the latter only being
+/// tested when the draw score would improve alpha.
 #[inline]
-pub(crate) fn is_pseudo_draw(board: &Board, alpha: f32, root: bool) -> bool {
-    board.get_halfmove() >= 100 || (!root && alpha < 0.0 && board.test_upcoming_repetition())
+pub(crate) fn is_pseudo_draw(board: &Board, alpha: f32, draw: f32, root: bool) -> bool {
+    board.is_fifty_move_draw() || (!root && alpha < draw && board.test_upcoming_repetition())
 }
 
 /// Returns true if the board can be considered in endgame.
@@ -40,13 +73,6 @@ pub(crate) fn is_endgame(board: &Board) -> bool {
     })
 }
 
-/// Returns the color the king is of that color is standing on.
-#[inline]
-pub(crate) fn king_sq_color(board: &Board, color: Color) -> Square {
-    // SAFE: there is always a king on the board.
-    unsafe {board.get_bitboard(color, Piece::King).as_square_unchecked()}
-}
-
 /// Returns true if any of our pawn may promote this turn.
 #[inline]
 pub(crate) fn may_promote(board: &Board) -> bool {
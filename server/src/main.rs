@@ -8,7 +8,7 @@ use log::LevelFilter;
 use simple_logger::SimpleLogger;
 use warp::Filter;
 
-use chess::board::Board;
+use chess::board::{Board, STARTPOS_FEN};
 use engine::Engine;
 
 mod game;
@@ -20,9 +20,6 @@ use crate::sockets::Sockets;
 /// The default address the server listens on.
 const DEFAULT_ADDRESS: &str = "127.0.0.1:5050";
 
-/// The fen used for the default position.
-const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
-
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
     // Gets the arguments.
@@ -45,7 +42,7 @@ async fn main() -> Result<()> {
         .arg(Arg::with_name("fen")
             .long("fen")
             .value_name("FEN")
-            .default_value(DEFAULT_FEN)
+            .default_value(STARTPOS_FEN)
             .help("Sets the fen string to use as the starting position, use double quotes to give everything in a single argument.")
             .takes_value(true))
         .arg(Arg::with_name("book")
@@ -1,22 +1,30 @@
-use js_sys::Error as JsError;
+use js_sys::{Array, Error as JsError, Object, Reflect};
 use wasm_bindgen::prelude::*;
 use wee_alloc::WeeAlloc;
 
+use std::cell::RefCell;
 use std::str::FromStr;
 
-use chess::board::Board;
+use chess::board::{Board, Status, STARTPOS_FEN};
 use chess::color::Color;
 use chess::piece::Piece;
 use chess::movegen;
 use chess::moves::Move;
 use chess::square::Square;
 
+use engine::Engine;
+
 // Use the wee_alloc allocator instead of the std one to save space.
 #[global_allocator]
 static ALLOC: WeeAlloc = WeeAlloc::INIT;
 
-// The default fen position, used to initialize the board.
-const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+// The size of the transposition table of the engine in megabytes, small enough for wasm's memory.
+const TABLE_SIZE_MB: usize = 4;
+
+thread_local! {
+    // The engine, once its network is loaded.
+    static ENGINE: RefCell<Option<Engine>> = const {RefCell::new(None)};
+}
 
 // Construct a javascript Error as a JsValue, from something that implements fmt::Display.
 fn js_error(msg: &str) -> JsValue {
@@ -28,6 +36,76 @@ fn parse_square(sq: &str) -> Result<Square, JsValue> {
     Square::from_str(sq).map_err(|_| js_error("Invalid square literal."))
 }
 
+// Sets a string property of a javascript object.
+fn set_property(object: &Object, key: &str, value: &str) -> Result<(), JsValue> {
+    Reflect::set(object, &JsValue::from_str(key), &JsValue::from_str(value)).map(|_| ())
+}
+
+/// Returns the legal moves of the position given by a fen string, as an array
+/// of {from, to, uci, san} objects, the squares being given as "e2", "e4".
+#[wasm_bindgen(js_name = legalMoves)]
+pub fn legal_moves(fen: &str) -> Result<JsValue, JsValue> {
+    // Initialize the chess lib, if not done already.
+    chess::init();
+
+    let board = Board::new(fen).map_err(|_| js_error("Invalid fen literal."))?;
+    let mut legals = Vec::new();
+    movegen::legals(&board, &mut legals);
+
+    let moves = Array::new();
+    for mv in legals {
+        let object = Object::new();
+        set_property(&object, "from", &mv.from().to_string())?;
+        set_property(&object, "to", &mv.to().to_string())?;
+        set_property(&object, "uci", &mv.to_string())?;
+        set_property(&object, "san", &board.move_to_san(mv))?;
+        moves.push(&object);
+    }
+
+    Ok(moves.into())
+}
+
+/// Loads the network of the engine from the content of its file (nnue.bin),
+/// which must be done before asking the engine for a move.
+#[wasm_bindgen(js_name = loadNetwork)]
+pub fn load_network(bytes: &[u8]) -> Result<(), JsValue> {
+    // Initialize the chess lib, if not done already.
+    chess::init();
+
+    let board = Board::new(STARTPOS_FEN).unwrap();
+    let seed = (js_sys::Math::random() * f64::from(u32::MAX)) as u32;
+    let engine = Engine::single_threaded(board, bytes, TABLE_SIZE_MB, seed).map_err(|_| js_error("Invalid network file."))?;
+
+    ENGINE.with(|cell| *cell.borrow_mut() = Some(engine));
+
+    Ok(())
+}
+
+/// Returns the move the engine plays in the position given by a fen string, in uci notation,
+/// after searching it with iterative deepening up to the given depth, at least 1, on the
+/// current thread.
+#[wasm_bindgen(js_name = bestMove)]
+pub fn best_move(fen: &str, depth: u8) -> Result<String, JsValue> {
+    // Initialize the chess lib, if not done already.
+    chess::init();
+
+    if depth == 0 {
+        return Err(js_error("The depth must be at least 1."));
+    }
+
+    let board = Board::new(fen).map_err(|_| js_error("Invalid fen literal."))?;
+
+    ENGINE.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let engine = cell.as_mut().ok_or_else(|| js_error("The network of the engine is not loaded."))?;
+
+        *engine.write_board() = board;
+        let pv = engine.search_iterative(depth, |_| ());
+
+        pv.first().map(|mv| mv.to_string()).ok_or_else(|| js_error("No legal move in this position."))
+    })
+}
+
 /// The WasmChess struct, simply named "Chess" in JS is a class
 /// representing a chess board, and wrapping some of it's functionnalities.
 #[wasm_bindgen(js_name = Chess)]
@@ -45,7 +123,7 @@ impl WasmChess {
         // Initialize the chess lib, if not done already.
         chess::init();
 
-        let board = Board::new(DEFAULT_FEN).unwrap();
+        let board = Board::new(STARTPOS_FEN).unwrap();
         let mut legals = Vec::new();
         movegen::legals(&board, &mut legals);
 
@@ -106,4 +184,76 @@ impl WasmChess {
     pub fn to_string(&self) -> String {
         format!("{:?}", self)
     }
-}
\ No newline at end of file
+}
+
+/// The WasmGame struct, named "Game" in JS, is a class representing a game being
+/// played, from a starting position, keeping the moves played so they can be undone.
+#[wasm_bindgen(js_name = Game)]
+#[derive(Debug)]
+pub struct WasmGame {
+    board: Board,
+    moves: Vec<Move>,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    /// Constructs a new WasmGame object, starting from the position given by a fen string.
+    #[wasm_bindgen(constructor)]
+    pub fn new(fen: &str) -> Result<WasmGame, JsValue> {
+        // Initialize the chess lib, if not done already.
+        chess::init();
+
+        let board = Board::new(fen).map_err(|_| js_error("Invalid fen literal."))?;
+
+        Ok(WasmGame {board, moves: Vec::new()})
+    }
+
+    /// Plays the move given in uci notation (e2e4, e7e8q). Returns false, leaving
+    /// the game unchanged, if the move is invalid or illegal.
+    #[wasm_bindgen(js_name = makeMove)]
+    pub fn make_move(&mut self, uci: &str) -> bool {
+        // Parsing slices the literal, which must not fall inside a character.
+        if !uci.is_ascii() {
+            return false;
+        }
+
+        match self.board.parse_move(uci) {
+            Ok(mv) => {
+                self.board.do_move(mv);
+                self.moves.push(mv);
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Takes back the last move played. Returns false if there is no move to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.moves.pop() {
+            Some(mv) => {
+                self.board.undo_move(mv);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns the fen representation of the current position.
+    pub fn fen(&self) -> String {
+        self.board.to_string()
+    }
+
+    /// Returns the status of the game: "checkmate" or "stalemate" when the side to move
+    /// has no legal move, "draw" for the other draws, or "ongoing".
+    pub fn status(&self) -> String {
+        let status = if movegen::count_legals(&self.board) == 0 {
+            if self.board.get_checkers().not_empty() {"checkmate"} else {"stalemate"}
+        } else if matches!(self.board.status(), Status::Draw) {
+            "draw"
+        } else {
+            "ongoing"
+        };
+
+        String::from(status)
+    }
+}